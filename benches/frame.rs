@@ -0,0 +1,78 @@
+//! Frames with pooled buffers against a fresh allocation per frame, as frames had before, and
+//! a request answered by a server over a `Channel`.
+//!
+//! Run with `cargo bench --bench frame`.
+use std::{hint::black_box, sync::Arc, time::Duration};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use tcsp::{
+    adaptor::{Channel, Frame, FrameMeta},
+    ApplicationOptions, EchoCommand, TcspClient, TcspClientBuilder, TcspServerBuilder,
+};
+use tokio::{runtime::Runtime, sync::mpsc::channel};
+
+/// The size of a frame buffer, payload and room for headers.
+const FRAME_DATA_LENGTH: usize = 168;
+const PAYLOAD: [u8; 150] = [0x5a; 150];
+const SERVER_ID: u8 = 1;
+
+fn frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+    group.bench_function("allocated", |b| {
+        b.iter(|| {
+            let mut data = vec![0u8; FRAME_DATA_LENGTH];
+            data[16..16 + PAYLOAD.len()].copy_from_slice(black_box(&PAYLOAD));
+            black_box(data)
+        })
+    });
+    group.bench_function("pooled", |b| {
+        b.iter(|| black_box(Frame::new(FrameMeta::default(), black_box(&PAYLOAD))))
+    });
+    group.bench_function("pooled_in_flight", |b| {
+        // a few frames are always held, as by the queue and the handlers of a busy server
+        let mut in_flight = Vec::with_capacity(8);
+        b.iter(|| {
+            if in_flight.len() == 8 {
+                in_flight.clear();
+            }
+            in_flight.push(Frame::new(FrameMeta::default(), black_box(&PAYLOAD)));
+        })
+    });
+    group.finish();
+}
+
+/// A client linked by a `Channel` to a server running the echo application with `options`.
+fn echo_server(runtime: &Runtime, options: ApplicationOptions) -> TcspClient<Channel> {
+    let (to_server, from_client) = channel(32);
+    let (to_client, from_server) = channel(32);
+    runtime.block_on(async {
+        let server = TcspServerBuilder::new(Channel::new(to_client, from_client))
+            .with_application_options(Arc::new(EchoCommand {}), options)
+            .build()
+            .unwrap();
+        tokio::spawn(async move { server.listen().await });
+        TcspClientBuilder::new(Channel::new(to_server, from_server)).build()
+    })
+}
+
+fn round_trip(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("round_trip");
+    let content = &PAYLOAD[..100];
+    let client = echo_server(&runtime, ApplicationOptions::new());
+    group.bench_function("echo", |b| {
+        b.iter(|| runtime.block_on(client.echo(SERVER_ID, black_box(content))).unwrap())
+    });
+    // every response is kept for replay, and expires before its request id comes around
+    let client = echo_server(
+        &runtime,
+        ApplicationOptions::new().dedup(Duration::from_nanos(1)),
+    );
+    group.bench_function("echo_dedup", |b| {
+        b.iter(|| runtime.block_on(client.echo(SERVER_ID, black_box(content))).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, frame, round_trip);
+criterion_main!(benches);
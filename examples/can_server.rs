@@ -1,8 +1,8 @@
-use std::{num::ParseIntError, sync::Arc, time::Duration};
+use std::{num::ParseIntError, path::PathBuf, sync::Arc, time::Duration};
 
 use clap::Parser;
 use tcsp::{
-    EchoCommand, Reboot, ResetNetwork, TcspServerBuilder, TeleMetry, TimeSync, TyCanProtocol, UdpBackup, ZeromqSocket
+    AccessPolicy, ApplicationOptions, Diagnostics, EchoCommand, Reboot, ResetNetwork, TcspServerBuilder, TeleMetry, TimeSync, TyCanProtocol, UdpBackup, ZeromqSocket
 };
 
 fn parse_number(s: &str) -> Result<u8, ParseIntError> {
@@ -18,6 +18,9 @@ fn parse_number(s: &str) -> Result<u8, ParseIntError> {
 struct Args {
     #[arg(required = true,value_parser=parse_number)]
     can_id: u8,
+    /// A TOML file restricting which nodes may invoke which applications.
+    #[arg(long)]
+    access_policy: Option<PathBuf>,
 }
 
 mod common;
@@ -42,13 +45,26 @@ async fn main() {
 
     #[allow(clippy::unwrap_used)]
     let adaptor = TyCanProtocol::new(canid, "can0", "can0").await.unwrap();
-    let server = TcspServerBuilder::new_can(adaptor)
-        .with_application(Arc::new(TeleMetry::new(socket.clone())))
+    // the applications forwarding to the zeromq socket wait for its reply at most 100ms
+    let fallback_options = ApplicationOptions::new().timeout(Duration::from_millis(100));
+    // a retransmitted reboot or reset is answered again, but not executed twice
+    let once_options = ApplicationOptions::new().dedup(Duration::from_secs(10));
+    let access_policy = match &args.access_policy {
+        Some(path) => AccessPolicy::load(path).expect("Failed to load access policy"),
+        None => AccessPolicy::default(),
+    };
+    let server = TcspServerBuilder::new(adaptor)
+        .with_access_policy(access_policy)
+        .with_application_options(Arc::new(TeleMetry::new(socket.clone())), fallback_options.clone())
         .with_application(Arc::new(EchoCommand {}))
-        .with_application(Arc::new(TimeSync::new(socket.clone())))
-        .with_application(Arc::new(Reboot {}))
-        .with_application(Arc::new(UdpBackup::new(socket)))
-        .with_application(Arc::new(ResetNetwork{}))
-        .build();
-    server.listen().await;
+        .with_application(Arc::new(Diagnostics::new()))
+        .with_application_options(Arc::new(TimeSync::new(socket.clone())), fallback_options.clone())
+        .with_application_options(Arc::new(Reboot {}), once_options.clone())
+        .with_application_options(Arc::new(UdpBackup::new(socket)), fallback_options)
+        .with_application_options(Arc::new(ResetNetwork {}), once_options)
+        .build()
+        .expect("Failed to build server");
+    if let Err(e) = server.listen().await {
+        log::error!("server stopped with error:{}", e);
+    }
 }
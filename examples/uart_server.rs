@@ -1,6 +1,6 @@
 use std::{sync::Arc, time::Duration};
 
-use tcsp::{EchoCommand, Reboot, TcspServerBuilder, TeleMetry, TimeSync, Uart, UdpBackup, ZeromqSocket};
+use tcsp::{ApplicationOptions, Diagnostics, EchoCommand, Reboot, TcspServerBuilder, TeleMetry, TimeSync, Uart, UdpBackup, ZeromqSocket};
 
 mod common;
 use common::init_logger;
@@ -20,12 +20,20 @@ async fn main() {
     .expect("Failed to connect");
     #[allow(clippy::unwrap_used)]
     let adaptor = Uart::new("/dev/ttyAMA1", 115200).await;
-    let server = TcspServerBuilder::new_uart(adaptor)
-        .with_application(Arc::new(TeleMetry::new(socket.clone())))
+    // the applications forwarding to the zeromq socket wait for its reply at most 100ms
+    let fallback_options = ApplicationOptions::new().timeout(Duration::from_millis(100));
+    // a retransmitted reboot or reset is answered again, but not executed twice
+    let once_options = ApplicationOptions::new().dedup(Duration::from_secs(10));
+    let server = TcspServerBuilder::new(adaptor)
+        .with_application_options(Arc::new(TeleMetry::new(socket.clone())), fallback_options.clone())
         .with_application(Arc::new(EchoCommand {}))
-        .with_application(Arc::new(TimeSync::new(socket.clone())))
-        .with_application(Arc::new(Reboot {}))
-        .with_application(Arc::new(UdpBackup::new(socket)))
-        .build();
-    server.listen().await;
+        .with_application(Arc::new(Diagnostics::new()))
+        .with_application_options(Arc::new(TimeSync::new(socket.clone())), fallback_options.clone())
+        .with_application_options(Arc::new(Reboot {}), once_options)
+        .with_application_options(Arc::new(UdpBackup::new(socket)), fallback_options)
+        .build()
+        .expect("Failed to build server");
+    if let Err(e) = server.listen().await {
+        log::error!("server stopped with error:{}", e);
+    }
 }
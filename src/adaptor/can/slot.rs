@@ -52,6 +52,11 @@ impl Slot {
         Ok(())
     }
 
+    /// Whether a first frame opened this slot, so a middle frame has something to continue.
+    pub(super) fn is_open(&self) -> bool {
+        self.is_valid
+    }
+
     pub(super) fn is_complete(&self) -> bool {
         self.is_valid && self.current_len == self.total_len
     }
@@ -1,7 +1,7 @@
-use crate::adaptor::{DeviceAdaptor, DeviceAdaptorError};
+use crate::adaptor::{AdaptorCounters, AdaptorStats, DeviceAdaptor, DeviceAdaptorError};
 use crate::utils::has_root_privilege;
 
-use super::super::{Frame as BusFrame, FrameFlag, FrameMeta};
+use super::super::{frame::BROADCAST_ID, Frame as BusFrame, FrameFlag, FrameMeta};
 use async_trait::async_trait;
 use bitfield::bitfield;
 use futures_util::StreamExt;
@@ -40,7 +40,7 @@ const TY_CAN_PROTOCOL_UTILITES_MULTI_RESPONSE: u8 = 0x04;
 
 const TY_CAN_ID_FILTER_MASK: u32 = 0x1fe000;
 const TY_CAN_ID_OFFSET: usize = 13;
-const TY_CAN_BROADCAST_ID: u8 = 0xfd;
+const TY_CAN_BROADCAST_ID: u8 = BROADCAST_ID;
 const TY_CAN_OBC_ID: u8 = 0;
 
 #[cfg(feature = "netlink_can_error_detection")]
@@ -110,10 +110,13 @@ impl TySingleFrameHeader {
         self.type_ = type_;
     }
 
+    /// A request from the OBC, or a response to the OBC.
     fn check_valid(&self) -> bool {
-        self.utilites() == TY_CAN_PROTOCOL_UTILITES_SINGLE_REQUEST
+        (self.utilites() == TY_CAN_PROTOCOL_UTILITES_SINGLE_REQUEST
             && (self.type_() == TY_CAN_PROTOCOL_TYPE_OBC_COMMAND_REQUEST
-                || self.type_() == TY_CAN_PROTOCOL_TYPE_OBC_BROADCAST_REQUEST)
+                || self.type_() == TY_CAN_PROTOCOL_TYPE_OBC_BROADCAST_REQUEST))
+            || (self.utilites() == TY_CAN_PROTOCOL_UTILITES_SINGLE_RESPONSE
+                && self.type_() == TY_CAN_PROTOCOL_TYPE_RESPONSE)
     }
 }
 impl TyMultiFrameHeader {
@@ -156,10 +159,13 @@ impl TyMultiFrameHeader {
         self.hdr.type_ = type_;
     }
 
+    /// A request from the OBC, or a response to the OBC.
     fn check_valid(&self) -> bool {
-        self.hdr.utilites() == TY_CAN_PROTOCOL_UTILITES_MULTI_REQUEST
+        (self.hdr.utilites() == TY_CAN_PROTOCOL_UTILITES_MULTI_REQUEST
             && (self.hdr.type_() == TY_CAN_PROTOCOL_TYPE_OBC_COMMAND_REQUEST
-                || self.hdr.type_() == TY_CAN_PROTOCOL_TYPE_OBC_BROADCAST_REQUEST)
+                || self.hdr.type_() == TY_CAN_PROTOCOL_TYPE_OBC_BROADCAST_REQUEST))
+            || (self.hdr.utilites() == TY_CAN_PROTOCOL_UTILITES_MULTI_RESPONSE
+                && self.hdr.type_() == TY_CAN_PROTOCOL_TYPE_RESPONSE)
     }
 }
 
@@ -171,7 +177,7 @@ pub struct TyCanProtocol {
     socket_tx: Mutex<AsyncCanSocket<CanSocket>>,
     socket_rx_name: String,
     socket_tx_name: String,
-    id_counter: AtomicU8,
+    stats: AdaptorCounters,
 }
 
 /// Safety: Only one thread is response for receiving packets.
@@ -212,39 +218,45 @@ enum TyCanProtocolFrameType {
 #[async_trait]
 impl DeviceAdaptor for TyCanProtocol {
     async fn recv(&self) -> Result<BusFrame, DeviceAdaptorError> {
-        let frame = self.socket_rx.lock().await.next().await;
-        if let Some(Ok(frame)) = frame {
-            match frame {
-                CanFrame::Data(data_frame) => {
-                    let ty_can_id = TyCanId(data_frame.raw_id());
-                    let frame_type = TyCanProtocolFrameType::try_from(ty_can_id.get_frame_type())
-                        .unwrap_or(TyCanProtocolFrameType::Unknown);
-                    if matches!(frame_type, TyCanProtocolFrameType::Reset) {
-                        if let Err(e) = self.restart() {
-                            log::error!("restart failed:{:?}", e);
-                        }
-                    } else {
-                        match recv(
-                            &self.slot_map,
-                            &data_frame,
-                            self.src_id.load(Ordering::Relaxed),
-                        ) {
-                            Ok(option_frame) => {
-                                if let Some(bus_frame) = option_frame {
-                                    return Ok(bus_frame);
-                                }
-                            }
-                            Err(e) => {
-                                log::error!("{}", e);
+        let frame = match self.socket_rx.lock().await.next().await {
+            Some(Ok(frame)) => frame,
+            Some(Err(e)) => return Err(e.into()),
+            // the socket stream only ends when the socket is gone
+            None => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+        };
+        match frame {
+            CanFrame::Data(data_frame) => {
+                let ty_can_id = TyCanId(data_frame.raw_id());
+                let frame_type = TyCanProtocolFrameType::try_from(ty_can_id.get_frame_type())
+                    .unwrap_or(TyCanProtocolFrameType::Unknown);
+                if matches!(frame_type, TyCanProtocolFrameType::Reset) {
+                    if let Err(e) = self.restart() {
+                        log::error!("restart failed:{:?}", e);
+                    }
+                } else {
+                    match recv(
+                        &self.slot_map,
+                        &data_frame,
+                        self.src_id.load(Ordering::Relaxed),
+                        &self.stats,
+                    ) {
+                        Ok(option_frame) => {
+                            if let Some(bus_frame) = option_frame {
+                                self.stats.received.incr();
+                                return Ok(bus_frame);
                             }
                         }
+                        Err(e) => {
+                            self.stats.parse_errors.incr();
+                            log::error!("{}", e);
+                        }
                     }
                 }
-                CanFrame::Error(error_frame) => {
-                    log::info!("{:?}", error_frame);
-                }
-                _ => {}
             }
+            CanFrame::Error(error_frame) => {
+                log::info!("{:?}", error_frame);
+            }
+            _ => {}
         }
         Err(DeviceAdaptorError::Empty)
     }
@@ -257,18 +269,36 @@ impl DeviceAdaptor for TyCanProtocol {
     /// Othersewise, the send will send a response as default.
     ///
     /// When sending a Time broadcast, the caller should provide a 4 bytes buffer and set the `CanTimeBroadcast` flag.
+    ///
+    /// The pid of the can id is the `id` of the frame, so a response has the pid of its request.
     async fn send(&self, frame: BusFrame) -> Result<(), DeviceAdaptorError> {
         let src_id = self.src_id.load(Ordering::Relaxed);
-        let id = self
-            .id_counter
-            .fetch_add(1, std::sync::atomic::Ordering::AcqRel);
-        let _sended_can_frame = send_using_ty_protocol(&self.socket_tx, src_id, id, frame).await?;
+        let result = send_using_ty_protocol(&self.socket_tx, src_id, frame).await;
+        self.stats.record_send(&result);
+        let _sended_can_frame = result?;
         Ok(())
     }
 
     fn mtu(&self, _flag: FrameFlag) -> usize {
         TY_CAN_PROTOCOL_PAYLOAD_MAX_SIZE
     }
+
+    fn stats(&self) -> AdaptorStats {
+        self.stats.snapshot()
+    }
+
+    /// Bring the interfaces down and up, then open new sockets on them.
+    async fn reopen(&self) -> Result<(), DeviceAdaptorError> {
+        self.restart()?;
+        let (socket_rx, socket_tx) = Self::open_sockets(
+            self.src_id.load(Ordering::Relaxed),
+            &self.socket_rx_name,
+            &self.socket_tx_name,
+        )?;
+        *self.socket_rx.lock().await = socket_rx;
+        *self.socket_tx.lock().await = socket_tx;
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -292,10 +322,10 @@ impl WriteFrame for Mutex<Vec<CanFrame>> {
     }
 }
 
+/// Send `frame` as can frames whose pid is the `id` of the frame.
 pub(crate) async fn send_using_ty_protocol<CanSocketTx: WriteFrame>(
     can_socket_tx: &CanSocketTx,
     src_id: u8,
-    id: u8,
     mut frame: BusFrame,
 ) -> Result<usize, DeviceAdaptorError> {
     let len = frame.meta.len;
@@ -312,7 +342,7 @@ pub(crate) async fn send_using_ty_protocol<CanSocketTx: WriteFrame>(
     new_id.set_src_id(src_id);
     new_id.set_dest_id(frame.meta.dest_id);
     new_id.set_is_csp(false);
-    new_id.set_pid(id);
+    new_id.set_pid(frame.meta.id);
     if len <= TY_CAN_PROTOCOL_SINGLE_FRAME_MAX as u16 {
         attach_single_frame_hdr(is_obc, &mut frame)?;
         let new_len = frame.len();
@@ -374,6 +404,24 @@ pub(crate) async fn send_using_ty_protocol<CanSocketTx: WriteFrame>(
 impl TyCanProtocol {
     pub async fn new(id: u8, socket_rx_name: &str, socket_tx_name: &str) -> io::Result<Self> {
         Self::setup_can_interface(socket_tx_name, socket_rx_name).await?;
+        let (socket_rx, socket_tx) = Self::open_sockets(id, socket_rx_name, socket_tx_name)?;
+        Ok(Self {
+            src_id: id.into(),
+            slot_map: RecvBuf::default(),
+            socket_rx: socket_rx.into(),
+            socket_tx: socket_tx.into(),
+            socket_rx_name: socket_rx_name.to_owned(),
+            socket_tx_name: socket_tx_name.to_owned(),
+            stats: AdaptorCounters::default(),
+        })
+    }
+
+    /// Open the sockets, the rx socket only accepts frames to `id` or broadcast.
+    fn open_sockets(
+        id: u8,
+        socket_rx_name: &str,
+        socket_tx_name: &str,
+    ) -> io::Result<(AsyncCanSocket<CanSocket>, AsyncCanSocket<CanSocket>)> {
         let socket_rx = AsyncCanSocket::open(socket_rx_name)?;
         let socket_tx = AsyncCanSocket::open(socket_tx_name)?;
         socket_rx.set_filters(&[
@@ -389,15 +437,7 @@ impl TyCanProtocol {
             socket_tx_name,
             (id as u32) << TY_CAN_ID_OFFSET
         );
-        Ok(Self {
-            src_id: id.into(),
-            slot_map: RecvBuf::default(),
-            socket_rx: socket_rx.into(),
-            socket_tx: socket_tx.into(),
-            socket_rx_name: socket_rx_name.to_owned(),
-            socket_tx_name: socket_tx_name.to_owned(),
-            id_counter: AtomicU8::new(0),
-        })
+        Ok((socket_rx, socket_tx))
     }
 
     #[allow(clippy::unwrap_used)]
@@ -421,8 +461,6 @@ impl TyCanProtocol {
 
     fn restart(&self) -> io::Result<()> {
         log::info!("CAN socket restart");
-        self.id_counter
-            .store(0, std::sync::atomic::Ordering::Release);
         let socket_rx_name = self.socket_rx_name.as_ref();
         let socket_tx_name = self.socket_tx_name.as_ref();
         Self::reset_interfaces(socket_rx_name, socket_tx_name)?;
@@ -568,7 +606,12 @@ fn get_checksum(buf: &[u8]) -> u8 {
     sum
 }
 
-fn recv(slot_map: &RecvBuf, frame: &CanDataFrame, self_id: u8) -> io::Result<Option<BusFrame>> {
+fn recv(
+    slot_map: &RecvBuf,
+    frame: &CanDataFrame,
+    self_id: u8,
+    stats: &AdaptorCounters,
+) -> io::Result<Option<BusFrame>> {
     let ty_can_id = TyCanId(frame.raw_id());
     let is_csp = ty_can_id.get_is_csp();
     let src_id = ty_can_id.get_src_id();
@@ -629,8 +672,9 @@ fn recv(slot_map: &RecvBuf, frame: &CanDataFrame, self_id: u8) -> io::Result<Opt
                     let slot = unsafe { slot_map.get_mut_unchecked(idx.into()) };
                     slot.reset();
                     // 3 include total_len(2B) and checksum(1B)
-                    slot.set_total_len(hdr.total_len() + 3)?;
-                    slot.copy_from_slice(frame.data())?;
+                    slot.set_total_len(hdr.total_len() + 3)
+                        .and_then(|()| slot.copy_from_slice(frame.data()))
+                        .inspect_err(|_| stats.slot_overflows.incr())?;
                 } else {
                     return Err(io::Error::new(
                         io::ErrorKind::InvalidInput,
@@ -645,7 +689,15 @@ fn recv(slot_map: &RecvBuf, frame: &CanDataFrame, self_id: u8) -> io::Result<Opt
         }
         TyCanProtocolFrameType::MultiMiddle => {
             let slot = unsafe { slot_map.get_mut_unchecked(idx.into()) };
-            slot.copy_from_slice(frame.data())?;
+            // an orphan is a parse error, counted by the caller, not an overflow
+            if !slot.is_open() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("id={:?} continues no multi frame", idx),
+                ));
+            }
+            slot.copy_from_slice(frame.data())
+                .inspect_err(|_| stats.slot_overflows.incr())?;
             if slot.is_complete() {
                 // check checksum
                 let total_len = slot.total_len();
@@ -666,6 +718,7 @@ fn recv(slot_map: &RecvBuf, frame: &CanDataFrame, self_id: u8) -> io::Result<Opt
                     )
                     .map(Some)
                 } else {
+                    stats.checksum_errors.incr();
                     Err(io::Error::new(
                         io::ErrorKind::InvalidInput,
                         format!("id={:?} checksum failed,expect {:?}", idx, checksum),
@@ -723,7 +776,7 @@ mod tests {
             TY_CAN_PROTOCOL_UTILITES_MULTI_REQUEST, TY_CAN_PROTOCOL_UTILITES_MULTI_RESPONSE,
             TY_CAN_PROTOCOL_UTILITES_SINGLE_REQUEST, TY_CAN_PROTOCOL_UTILITES_SINGLE_RESPONSE,
         },
-        Frame, FrameFlag, FrameMeta,
+        AdaptorCounters, Frame, FrameFlag, FrameMeta,
     };
 
     use super::{attach_single_frame_hdr, TyCanId, TyCanProtocolFrameType};
@@ -788,7 +841,8 @@ mod tests {
         ];
         let frame: CanDataFrame = CanDataFrame::new(can_id, &data).unwrap();
         let slot_map = RecvBuf::default();
-        let frame = super::recv(&slot_map, &frame, 0x2a).unwrap().unwrap();
+        let stats = AdaptorCounters::default();
+        let frame = super::recv(&slot_map, &frame, 0x2a, &stats).unwrap().unwrap();
         assert_eq!(frame.len(), 6);
         assert_eq!(frame.meta.src_id, 0);
         assert_eq!(frame.meta.dest_id, 0x2a);
@@ -816,15 +870,15 @@ mod tests {
         let checksum = get_checksum(&data);
         data.push(checksum);
         let frame = CanDataFrame::new(first_can_id, &data[0..8]).unwrap();
-        assert!(super::recv(&slot_map, &frame, 0x2a).unwrap().is_none());
+        assert!(super::recv(&slot_map, &frame, 0x2a, &stats).unwrap().is_none());
         let frame = CanDataFrame::new(rest_can_id, &data[8..16]).unwrap();
-        assert!(super::recv(&slot_map, &frame, 0x2a).unwrap().is_none());
+        assert!(super::recv(&slot_map, &frame, 0x2a, &stats).unwrap().is_none());
         let frame = CanDataFrame::new(rest_can_id, &data[16..24]).unwrap();
-        assert!(super::recv(&slot_map, &frame, 0x2a).unwrap().is_none());
+        assert!(super::recv(&slot_map, &frame, 0x2a, &stats).unwrap().is_none());
         let frame = CanDataFrame::new(rest_can_id, &data[24..32]).unwrap();
-        assert!(super::recv(&slot_map, &frame, 0x2a).unwrap().is_none());
+        assert!(super::recv(&slot_map, &frame, 0x2a, &stats).unwrap().is_none());
         let frame: CanDataFrame = CanDataFrame::new(rest_can_id, &data[32..39]).unwrap();
-        let frame = super::recv(&slot_map, &frame, 0x2a).unwrap().unwrap();
+        let frame = super::recv(&slot_map, &frame, 0x2a, &stats).unwrap().unwrap();
         assert_eq!(frame.meta.len, 39 - 4 - 1);
         assert_eq!(frame.meta.src_id, 0);
         assert_eq!(frame.meta.dest_id, 0x2a);
@@ -849,16 +903,111 @@ mod tests {
             0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
             0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0xa0];
         let frame = CanDataFrame::new(first_can_id, &data[0..8]).unwrap();
-        assert!(super::recv(&slot_map, &frame, 0x44).unwrap().is_none());
+        assert!(super::recv(&slot_map, &frame, 0x44, &stats).unwrap().is_none());
         for chunk in data[8..].chunks(8) {
             let frame = CanDataFrame::new(rest_can_id, chunk).unwrap();
-            if let Some(result) = super::recv(&slot_map, &frame, 0x44).unwrap(){
+            if let Some(result) = super::recv(&slot_map, &frame, 0x44, &stats).unwrap(){
                 println!("{:?}",result);
             }
         }
        
     }
 
+    #[test]
+    fn test_ty_protocol_recv_response() {
+        // a node answers the OBC, with the pid of the request
+        let mut id = TyCanId(0);
+        id.set_src_id(0x2a);
+        id.set_dest_id(0);
+        id.set_frame_type(TyCanProtocolFrameType::Single as u8);
+        id.set_is_csp(false);
+        id.set_pid(0x12);
+        let can_id = ExtendedId::new(id.0).unwrap();
+        let data = [
+            TY_CAN_PROTOCOL_TYPE_RESPONSE,
+            TY_CAN_PROTOCOL_UTILITES_SINGLE_RESPONSE,
+            0x20,
+            0x02,
+            0x05,
+        ];
+        let frame: CanDataFrame = CanDataFrame::new(can_id, &data).unwrap();
+        let slot_map = RecvBuf::default();
+        let stats = AdaptorCounters::default();
+        let frame = super::recv(&slot_map, &frame, 0, &stats).unwrap().unwrap();
+        assert_eq!(frame.meta.src_id, 0x2a);
+        assert_eq!(frame.meta.id, 0x12);
+        assert_eq!(frame.data(), &data[2..]);
+    }
+
+    #[test]
+    fn test_ty_protocol_recv_errors() {
+        let mut id = TyCanId(0);
+        id.set_src_id(0);
+        id.set_dest_id(0x2a);
+        id.set_frame_type(TyCanProtocolFrameType::MultiFirst as u8);
+        id.set_is_csp(false);
+        id.set_pid(0x21);
+        let first_can_id = ExtendedId::new(id.0).unwrap();
+        id.set_frame_type(TyCanProtocolFrameType::MultiMiddle as u8);
+        let rest_can_id = ExtendedId::new(id.0).unwrap();
+        let mut data = [
+            0,
+            0x0a_u8,
+            TY_CAN_PROTOCOL_TYPE_OBC_COMMAND_REQUEST,
+            TY_CAN_PROTOCOL_UTILITES_MULTI_REQUEST,
+        ]
+        .into_iter()
+        .chain(1..=8)
+        .collect::<Vec<u8>>();
+        let checksum = get_checksum(&data);
+        data.push(checksum.wrapping_add(1));
+        let slot_map = RecvBuf::default();
+        let stats = AdaptorCounters::default();
+
+        let frame = CanDataFrame::new(first_can_id, &data[0..8]).unwrap();
+        assert!(super::recv(&slot_map, &frame, 0x2a, &stats).unwrap().is_none());
+        let frame = CanDataFrame::new(rest_can_id, &data[8..]).unwrap();
+        assert!(super::recv(&slot_map, &frame, 0x2a, &stats).is_err());
+        assert_eq!(stats.checksum_errors.get(), 1);
+
+        // the slot was reset, so a middle frame has nothing to continue
+        assert!(super::recv(&slot_map, &frame, 0x2a, &stats).is_err());
+        assert_eq!(stats.slot_overflows.get(), 0);
+        assert_eq!(stats.checksum_errors.get(), 1);
+
+        // a middle frame longer than the rest of an open slot overflows it
+        let frame = CanDataFrame::new(first_can_id, &data[0..8]).unwrap();
+        assert!(super::recv(&slot_map, &frame, 0x2a, &stats).unwrap().is_none());
+        let frame = CanDataFrame::new(rest_can_id, &[0; 8]).unwrap();
+        assert!(super::recv(&slot_map, &frame, 0x2a, &stats).is_err());
+        assert_eq!(stats.slot_overflows.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ty_protocol_send_response_pid() {
+        // the response to a request of pid 0x12 from the OBC
+        let meta = FrameMeta {
+            src_id: 0x2a,
+            dest_id: 0,
+            id: 0x12,
+            ..Default::default()
+        };
+        let frame = Frame::new(meta, &[1, 2, 3]).unwrap();
+        let can_frames = tokio::sync::Mutex::new(Vec::new());
+        super::send_using_ty_protocol(&can_frames, 0x2a, frame)
+            .await
+            .unwrap();
+        let can_frames = can_frames.lock().await;
+        let [socketcan::CanFrame::Data(can_frame)] = can_frames.as_slice() else {
+            panic!("a short response is one data frame");
+        };
+        let id = TyCanId(socketcan::Frame::raw_id(can_frame));
+        assert_eq!(id.get_pid(), 0x12);
+        assert_eq!(id.get_src_id(), 0x2a);
+        assert_eq!(id.get_dest_id(), 0);
+        assert_eq!(can_frame.data()[0], TY_CAN_PROTOCOL_TYPE_RESPONSE);
+    }
+
     #[test]
     fn test_ty_protocol_send() {
         let data = [1, 2, 3, 4, 5, 6];
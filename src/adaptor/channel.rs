@@ -6,7 +6,7 @@ use tokio::sync::{
     Mutex,
 };
 
-use super::{DeviceAdaptor, DeviceAdaptorError, Frame, FrameFlag};
+use super::{AdaptorCounters, AdaptorStats, DeviceAdaptor, DeviceAdaptorError, Frame, FrameFlag};
 
 /// `Channel` is used for debugging currenly. With `Channel`, you can run the upper service without real hardware.
 pub struct Channel(Arc<ChannelInner>);
@@ -14,14 +14,16 @@ pub struct Channel(Arc<ChannelInner>);
 struct ChannelInner {
     tx: Sender<Frame>,
     rx: Mutex<Receiver<Frame>>,
+    stats: AdaptorCounters,
 }
 
 impl Channel {
-    #[cfg(test)]
+    /// A link sending into `tx` and receiving from `rx`, like one end of a bus.
     pub fn new(tx: Sender<Frame>, rx: Receiver<Frame>) -> Self {
         Self(Arc::new(ChannelInner {
             tx,
             rx: Mutex::new(rx),
+            stats: AdaptorCounters::default(),
         }))
     }
 }
@@ -29,19 +31,45 @@ impl Channel {
 #[async_trait]
 impl DeviceAdaptor for Channel {
     async fn send(&self, frame: Frame) -> Result<(), DeviceAdaptorError> {
-        self.0
-            .tx
-            .send(frame)
-            .await
-            .map_err(|e| DeviceAdaptorError::BusError(Box::new(e)))
+        let result = self.0.tx.send(frame).await;
+        self.0.stats.record_send(&result);
+        result.map_err(|e| DeviceAdaptorError::BusError(Box::new(e)))
     }
 
     async fn recv(&self) -> Result<Frame, DeviceAdaptorError> {
         let mut lock = self.0.rx.lock().await;
-        lock.recv().await.ok_or(DeviceAdaptorError::Empty)
+        // once the sending half is dropped, no frame ever arrives again
+        let frame = lock
+            .recv()
+            .await
+            .ok_or_else(|| DeviceAdaptorError::Disconnected("channel closed".into()))?;
+        self.0.stats.received.incr();
+        Ok(frame)
     }
 
     fn mtu(&self, _flag: FrameFlag) -> usize {
         150
     }
+
+    fn stats(&self) -> AdaptorStats {
+        self.0.stats.snapshot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::channel;
+
+    use super::Channel;
+    use crate::adaptor::DeviceAdaptor;
+
+    #[tokio::test]
+    async fn test_closed_channel_is_disconnected() {
+        let (tx, _rx) = channel(1);
+        let (peer, rx) = channel(1);
+        let channel = Channel::new(tx, rx);
+        drop(peer);
+        let error = channel.recv().await.unwrap_err();
+        assert!(error.is_fatal());
+    }
 }
@@ -10,19 +10,73 @@ pub enum DeviceAdaptorError {
     #[error("Bus error:{:?}", 0)]
     BusError(Box<dyn std::error::Error>),
 
+    /// The device is unplugged or down, and stays unusable until `DeviceAdaptor::reopen` succeeds.
+    #[error("Device disconnected:{0}")]
+    Disconnected(Box<dyn std::error::Error + Send + Sync>),
+
     #[error("No data available now")]
     Empty,
 }
 
 unsafe impl Send for DeviceAdaptorError {}
 
+impl DeviceAdaptorError {
+    /// Whether the device has to be reopened. Other errors are transient, the next call may succeed.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Self::Disconnected(_))
+    }
+}
+
 impl From<socketcan::Error> for DeviceAdaptorError {
     fn from(error: socketcan::Error) -> Self {
-        Self::BusError(Box::new(error))
+        match error {
+            socketcan::Error::Io(error) => error.into(),
+            error => Self::BusError(Box::new(error)),
+        }
     }
 }
 impl From<io::Error> for DeviceAdaptorError {
     fn from(error: io::Error) -> Self {
-        Self::BusError(Box::new(error))
+        if is_disconnected(&error) {
+            Self::Disconnected(Box::new(error))
+        } else {
+            Self::BusError(Box::new(error))
+        }
+    }
+}
+
+/// Whether `error` means the device is gone, rather than a single operation failed.
+fn is_disconnected(error: &io::Error) -> bool {
+    let gone = matches!(
+        error.kind(),
+        io::ErrorKind::NotFound
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::UnexpectedEof
+    );
+    gone || matches!(
+        error.raw_os_error(),
+        Some(libc::EIO | libc::ENODEV | libc::ENXIO | libc::ENETDOWN)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::DeviceAdaptorError;
+
+    #[test]
+    fn test_error_classification() {
+        let unplugged = DeviceAdaptorError::from(io::Error::from_raw_os_error(libc::ENODEV));
+        assert!(unplugged.is_fatal());
+        let down = DeviceAdaptorError::from(socketcan::Error::Io(io::Error::from_raw_os_error(
+            libc::ENETDOWN,
+        )));
+        assert!(down.is_fatal());
+        assert!(!DeviceAdaptorError::from(io::Error::from(io::ErrorKind::TimedOut)).is_fatal());
+        assert!(!DeviceAdaptorError::Empty.is_fatal());
     }
-}
\ No newline at end of file
+}
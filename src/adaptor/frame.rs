@@ -2,11 +2,29 @@ use std::io;
 
 use bitflags::bitflags;
 
+use super::pool::{Buffer, BufferPool};
+
 const FRAME_MAX_LENGTH: usize = 150;
 const FRAME_PADDING: usize = 18;
 const FRAME_DATA_LENGTH: usize = FRAME_MAX_LENGTH + FRAME_PADDING;
 const FRAME_DEFAULT_START_OFFSET: u16 = 16;
 
+static FRAME_POOL: BufferPool = BufferPool::new(FRAME_DATA_LENGTH);
+
+/// The destination id of frames sent to every node.
+pub(crate) const BROADCAST_ID: u8 = 0xfd;
+
+/// Identifies one of the adaptors a server listens on, in the order they were given to the builder.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AdaptorId(pub(crate) usize);
+
+impl AdaptorId {
+    /// The position of the adaptor among those given to the server builder.
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct FrameMeta {
     pub(crate) src_id: u8,
@@ -16,12 +34,34 @@ pub struct FrameMeta {
     pub(crate) data_type: u8,
     pub(crate) command_type: u8,
     pub(crate) flag: FrameFlag,
+    /// The adaptor the frame was received from. Set by the server.
+    pub(crate) adaptor: AdaptorId,
 }
 
 impl FrameMeta {
+    /// The node which sent the frame.
+    pub fn src_id(&self) -> u8 {
+        self.src_id
+    }
+
+    /// The node the frame is addressed to.
+    pub fn dest_id(&self) -> u8 {
+        self.dest_id
+    }
+
+    /// Identifies a request, and is copied into its response.
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
     pub fn exchange_src_dest(&mut self) {
         std::mem::swap(&mut self.src_id, &mut self.dest_id);
     }
+
+    /// Whether the frame is addressed to every node on the bus.
+    pub fn is_broadcast(&self) -> bool {
+        self.flag.contains(FrameFlag::CanTimeBroadcast) || self.dest_id == BROADCAST_ID
+    }
 }
 
 bitflags! {
@@ -35,6 +75,7 @@ bitflags! {
 /// A `Frame` is a data structure that report meta and data payload of Can, Uart or other bus frame
 ///
 /// The `Frame` use a fixed size(which is `FRAME_DATA_LENGTH`) of u8 buffer, and it is allocated on the heap.
+/// The buffers are pooled: a dropped frame gives its buffer to the next one, so frames rarely allocate.
 /// The frame's buffer can expand or shrink at a certain range. For the heading side, you can expand at most `FRAME_DEFAULT_START_OFFSET` bytes.
 /// And at the ending side, you can expand only FRAME_PADDING - FRAME_DEFAULT_START_OFFSET bytes, which is 2 bytes currently.
 /// When you call methods like `expand_` or `shrink`, the field `length` in `meta` will change at the same. We will move length to a private field sooner.
@@ -43,11 +84,12 @@ bitflags! {
 pub struct Frame {
     pub(crate) meta: FrameMeta,
     offset: u16,
-    data: Vec<u8>,
+    data: Buffer,
 }
 
 impl Frame {
-    pub(crate) fn new(meta: FrameMeta, data: &[u8]) -> io::Result<Self> {
+    /// A frame carrying a copy of `data`, which is at most 150 bytes.
+    pub fn new(meta: FrameMeta, data: &[u8]) -> io::Result<Self> {
         if data.len() > FRAME_MAX_LENGTH {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
@@ -57,20 +99,7 @@ impl Frame {
         let mut frame = Frame {
             meta,
             offset: FRAME_DEFAULT_START_OFFSET,
-            data: vec![0; FRAME_DATA_LENGTH],
-        };
-        frame.data
-            [FRAME_DEFAULT_START_OFFSET.into()..(FRAME_DEFAULT_START_OFFSET as usize + data.len())]
-            .copy_from_slice(data);
-        frame.meta.len = data.len() as u16;
-        Ok(frame)
-    }
-
-    pub(crate) fn extended(meta: FrameMeta, data: &[u8]) -> io::Result<Self> {
-        let mut frame = Frame {
-            meta,
-            offset: FRAME_DEFAULT_START_OFFSET,
-            data: vec![0; data.len() + FRAME_PADDING],
+            data: FRAME_POOL.take(),
         };
         frame.data
             [FRAME_DEFAULT_START_OFFSET.into()..(FRAME_DEFAULT_START_OFFSET as usize + data.len())]
@@ -125,13 +154,24 @@ impl Frame {
         Ok(())
     }
 
-    pub(crate) fn data(&self) -> &[u8] {
+    /// Another handle to this frame, sharing its buffer until one of them is written.
+    ///
+    /// Unlike `clone`, the data is not copied, which suits frames sent and kept at the same time.
+    pub(crate) fn share(&mut self) -> Self {
+        Self {
+            meta: self.meta,
+            offset: self.offset,
+            data: self.data.share(),
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
         let start = self.offset as usize;
         let end = start + self.meta.len as usize;
         &self.data[start..end]
     }
 
-    pub(crate) fn data_mut(&mut self) -> &mut [u8] {
+    pub fn data_mut(&mut self) -> &mut [u8] {
         let start = self.offset as usize;
         let end = start + self.meta.len as usize;
         &mut self.data[start..end]
@@ -143,7 +183,7 @@ impl Default for Frame {
         Self {
             meta: Default::default(),
             offset: FRAME_DEFAULT_START_OFFSET,
-            data: vec![0; FRAME_DATA_LENGTH],
+            data: FRAME_POOL.take(),
         }
     }
 }
@@ -1,18 +1,26 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
 
 mod can;
 mod channel;
 mod error;
 mod frame;
+mod pool;
+mod stats;
 mod uart;
+mod udp;
 
 pub use can::ty::TyCanProtocol;
 pub(crate) use can::ty::send_using_ty_protocol;
 pub use channel::Channel;
 pub use error::DeviceAdaptorError;
-pub use frame::{Frame, FrameFlag, FrameMeta};
+pub use frame::{AdaptorId, Frame, FrameFlag, FrameMeta};
+pub use stats::AdaptorStats;
+pub(crate) use stats::AdaptorCounters;
 pub use uart::TyUartProtocol;
 pub use uart::Uart;
+pub use udp::Udp;
 
 #[async_trait]
 pub trait DeviceAdaptor: Send + Sync {
@@ -27,4 +35,88 @@ pub trait DeviceAdaptor: Send + Sync {
     ///
     /// Some devices like uart may have different mtu when giving different `FrameFlag`.
     fn mtu(&self, flag: FrameFlag) -> usize;
+
+    /// Wait until the frames handed to `send` are written out to the bus.
+    async fn flush(&self) -> Result<(), DeviceAdaptorError> {
+        Ok(())
+    }
+
+    /// The counters of the adaptor since it was created. Adaptors without counters return zeros.
+    fn stats(&self) -> AdaptorStats {
+        AdaptorStats::default()
+    }
+
+    /// Reopen the device after a fatal error, see `DeviceAdaptorError::is_fatal`.
+    /// Adaptors which can not lose their device do nothing.
+    async fn reopen(&self) -> Result<(), DeviceAdaptorError> {
+        Ok(())
+    }
+
+    /// Whether the frames carry the ids of their source and destination nodes.
+    /// A point to point link like a uart does not: its frames come from the node at the other end.
+    fn addressed(&self) -> bool {
+        true
+    }
+}
+
+#[async_trait]
+impl<T: DeviceAdaptor + ?Sized> DeviceAdaptor for Box<T> {
+    async fn send(&self, frame: Frame) -> Result<(), DeviceAdaptorError> {
+        (**self).send(frame).await
+    }
+
+    async fn recv(&self) -> Result<Frame, DeviceAdaptorError> {
+        (**self).recv().await
+    }
+
+    fn mtu(&self, flag: FrameFlag) -> usize {
+        (**self).mtu(flag)
+    }
+
+    async fn flush(&self) -> Result<(), DeviceAdaptorError> {
+        (**self).flush().await
+    }
+
+    fn stats(&self) -> AdaptorStats {
+        (**self).stats()
+    }
+
+    async fn reopen(&self) -> Result<(), DeviceAdaptorError> {
+        (**self).reopen().await
+    }
+
+    fn addressed(&self) -> bool {
+        (**self).addressed()
+    }
+}
+
+#[async_trait]
+impl<T: DeviceAdaptor + ?Sized> DeviceAdaptor for Arc<T> {
+    async fn send(&self, frame: Frame) -> Result<(), DeviceAdaptorError> {
+        (**self).send(frame).await
+    }
+
+    async fn recv(&self) -> Result<Frame, DeviceAdaptorError> {
+        (**self).recv().await
+    }
+
+    fn mtu(&self, flag: FrameFlag) -> usize {
+        (**self).mtu(flag)
+    }
+
+    async fn flush(&self) -> Result<(), DeviceAdaptorError> {
+        (**self).flush().await
+    }
+
+    fn stats(&self) -> AdaptorStats {
+        (**self).stats()
+    }
+
+    async fn reopen(&self) -> Result<(), DeviceAdaptorError> {
+        (**self).reopen().await
+    }
+
+    fn addressed(&self) -> bool {
+        (**self).addressed()
+    }
 }
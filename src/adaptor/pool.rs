@@ -0,0 +1,198 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+/// The most buffers kept for reuse. The rest are freed when their frames are dropped.
+const POOL_CAPACITY: usize = 64;
+
+/// Buffers of one size, kept when their frames are dropped and reused by the next frames.
+///
+/// A frame is received, handled and answered on different tasks, so the pool is shared
+/// rather than per thread.
+pub(crate) struct BufferPool {
+    len: usize,
+    buffers: Mutex<Vec<Vec<u8>>>,
+}
+
+impl BufferPool {
+    pub(crate) const fn new(len: usize) -> Self {
+        Self {
+            len,
+            buffers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// A zeroed buffer of the pool's size.
+    pub(crate) fn take(&'static self) -> Buffer {
+        Buffer {
+            data: Data::Unique(self.take_data()),
+            pool: Some(self),
+        }
+    }
+
+    fn take_data(&self) -> Vec<u8> {
+        let reused = self
+            .buffers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop();
+        match reused {
+            Some(mut data) => {
+                data.fill(0);
+                data
+            }
+            None => vec![0; self.len],
+        }
+    }
+
+    fn give_back(&self, data: Vec<u8>) {
+        let mut buffers = self.buffers.lock().unwrap_or_else(PoisonError::into_inner);
+        if buffers.len() < POOL_CAPACITY {
+            buffers.push(data);
+        }
+    }
+}
+
+impl std::fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferPool")
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+/// The buffer of a frame, which returns to its pool when dropped.
+#[derive(Debug)]
+pub(crate) struct Buffer {
+    data: Data,
+    pool: Option<&'static BufferPool>,
+}
+
+#[derive(Debug)]
+enum Data {
+    Unique(Vec<u8>),
+    /// Shared by the handles of `Buffer::share`, until one of them is written.
+    Shared(Arc<Vec<u8>>),
+}
+
+impl Buffer {
+    /// Another handle to this buffer, without copying it, as for a response which is both sent
+    /// and kept for replay. The first handle written gets a copy of its own.
+    pub(crate) fn share(&mut self) -> Self {
+        let shared = match &mut self.data {
+            Data::Unique(data) => Arc::new(std::mem::take(data)),
+            Data::Shared(shared) => Arc::clone(shared),
+        };
+        self.data = Data::Shared(Arc::clone(&shared));
+        Self {
+            data: Data::Shared(shared),
+            pool: self.pool,
+        }
+    }
+
+    /// The bytes of the buffer, copied into a buffer of the same pool.
+    fn copy(&self) -> Vec<u8> {
+        let mut data = match self.pool {
+            Some(pool) => pool.take_data(),
+            None => vec![0; self.len()],
+        };
+        data.copy_from_slice(self);
+        data
+    }
+}
+
+impl Clone for Buffer {
+    fn clone(&self) -> Self {
+        Self {
+            data: Data::Unique(self.copy()),
+            pool: self.pool,
+        }
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        let Some(pool) = self.pool else {
+            return;
+        };
+        let data = match std::mem::replace(&mut self.data, Data::Unique(Vec::new())) {
+            Data::Unique(data) => data,
+            // the last handle gives the buffer back
+            Data::Shared(shared) => match Arc::try_unwrap(shared) {
+                Ok(data) => data,
+                Err(_) => return,
+            },
+        };
+        pool.give_back(data);
+    }
+}
+
+impl Deref for Buffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.data {
+            Data::Unique(data) => data,
+            Data::Shared(shared) => shared,
+        }
+    }
+}
+
+impl DerefMut for Buffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        if let Data::Shared(shared) = &mut self.data {
+            let data = match Arc::get_mut(shared) {
+                Some(data) => std::mem::take(data),
+                None => self.copy(),
+            };
+            self.data = Data::Unique(data);
+        }
+        match &mut self.data {
+            Data::Unique(data) => data,
+            Data::Shared(_) => &mut [],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BufferPool, POOL_CAPACITY};
+
+    static POOL: BufferPool = BufferPool::new(8);
+
+    #[test]
+    fn test_buffer_reuse() {
+        let mut buffer = POOL.take();
+        buffer[0] = 1;
+        let ptr = buffer.as_ptr();
+        drop(buffer);
+        let reused = POOL.take();
+        assert_eq!(reused.as_ptr(), ptr);
+        assert_eq!(&reused[..], [0; 8]);
+
+        let copy = reused.clone();
+        assert_ne!(copy.as_ptr(), ptr);
+        drop(reused);
+        drop(copy);
+
+        // a shared buffer is copied when written, and given back by its last handle
+        let mut buffer = POOL.take();
+        let ptr = buffer.as_ptr();
+        let mut kept = buffer.share();
+        assert_eq!(kept.as_ptr(), ptr);
+        kept[0] = 2;
+        assert_ne!(kept.as_ptr(), ptr);
+        assert_eq!((buffer[0], kept[0]), (0, 2));
+        let kept = buffer.share();
+        drop(buffer);
+        drop(kept);
+        assert_eq!(POOL.take().as_ptr(), ptr);
+
+        let buffers = (0..POOL_CAPACITY + 1)
+            .map(|_| POOL.take())
+            .collect::<Vec<_>>();
+        drop(buffers);
+        assert_eq!(POOL.buffers.lock().unwrap().len(), POOL_CAPACITY);
+    }
+}
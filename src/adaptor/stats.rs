@@ -0,0 +1,52 @@
+use crate::utils::Counter;
+
+/// The counters of an adaptor, returned by `DeviceAdaptor::stats`.
+///
+/// An adaptor only fills in the counters which apply to its bus, the others stay zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AdaptorStats {
+    /// Frames handed to the server.
+    pub received: u64,
+    /// Frames written to the bus.
+    pub sent: u64,
+    /// Frames which could not be written to the bus.
+    pub send_errors: u64,
+    /// Received frames discarded as malformed, including checksum failures and slot overflows.
+    pub parse_errors: u64,
+    /// Multi-frame packets discarded because their checksum did not match.
+    pub checksum_errors: u64,
+    /// Multi-frame packets discarded because they did not fit in their receive slot.
+    pub slot_overflows: u64,
+}
+
+/// The live counters behind `AdaptorStats`, updated by the adaptor as frames go through.
+#[derive(Debug, Default)]
+pub(crate) struct AdaptorCounters {
+    pub(crate) received: Counter,
+    pub(crate) sent: Counter,
+    pub(crate) send_errors: Counter,
+    pub(crate) parse_errors: Counter,
+    pub(crate) checksum_errors: Counter,
+    pub(crate) slot_overflows: Counter,
+}
+
+impl AdaptorCounters {
+    pub(crate) fn snapshot(&self) -> AdaptorStats {
+        AdaptorStats {
+            received: self.received.get(),
+            sent: self.sent.get(),
+            send_errors: self.send_errors.get(),
+            parse_errors: self.parse_errors.get(),
+            checksum_errors: self.checksum_errors.get(),
+            slot_overflows: self.slot_overflows.get(),
+        }
+    }
+
+    /// Count the outcome of a send.
+    pub(crate) fn record_send<T, E>(&self, result: &Result<T, E>) {
+        match result {
+            Ok(_) => self.sent.incr(),
+            Err(_) => self.send_errors.incr(),
+        }
+    }
+}
@@ -1,5 +1,7 @@
 #![allow(clippy::shadow_unrelated, clippy::unwrap_used)]
 use std::convert::Into;
+use std::io;
+use std::sync::{Arc, Mutex as BlockingMutex, PoisonError};
 use std::time::Duration;
 
 use async_trait::async_trait;
@@ -9,7 +11,9 @@ use nom::{bytes::complete::take, combinator::map_res, error::ErrorKind, sequence
 use serialport::SerialPort;
 use tokio::sync::Mutex;
 
-use super::{DeviceAdaptor, Frame, FrameFlag, FrameMeta};
+use super::{
+    AdaptorCounters, AdaptorStats, DeviceAdaptor, DeviceAdaptorError, Frame, FrameFlag, FrameMeta,
+};
 
 #[cfg(feature = "unstable_add_frameheader")]
 use crate::protocol::v1::frame::FrameHeader;
@@ -28,30 +32,60 @@ const CUSTOM_ALG: crc::Algorithm<u8> = crc::Algorithm {
 #[derive(Debug)]
 pub struct Uart {
     file: Mutex<Box<dyn SerialPort>>,
+    /// Another handle of the port, read by a blocking task.
+    reader: Arc<BlockingMutex<Box<dyn SerialPort>>>,
+    device_name: String,
+    baud_rate: u32,
+    stats: AdaptorCounters,
 }
 
 impl Uart {
     pub async fn new(device_name: &str, baud_rate: u32) -> Self {
-        let port = serialport::new(device_name, baud_rate)
-            .timeout(Duration::from_secs(5))
-            .open()
-            .unwrap();
+        let port = open_port(device_name, baud_rate).unwrap();
+        Self {
+            reader: Arc::new(BlockingMutex::new(port.try_clone().unwrap())),
+            file: Mutex::new(port),
+            device_name: device_name.to_owned(),
+            baud_rate,
+            stats: AdaptorCounters::default(),
+        }
+    }
+
+    /// A uart on an open `port`, like one end of a pseudo terminal.
+    #[cfg(test)]
+    pub(crate) fn from_port(port: Box<dyn SerialPort>) -> Self {
         Self {
+            reader: Arc::new(BlockingMutex::new(port.try_clone().unwrap())),
             file: Mutex::new(port),
+            device_name: String::new(),
+            baud_rate: 0,
+            stats: AdaptorCounters::default(),
         }
     }
 }
 
+fn open_port(device_name: &str, baud_rate: u32) -> io::Result<Box<dyn SerialPort>> {
+    Ok(serialport::new(device_name, baud_rate)
+        .timeout(Duration::from_secs(5))
+        .open()?)
+}
+
 #[async_trait]
 impl DeviceAdaptor for Uart {
-    async fn send(&self, buf: super::Frame) -> Result<(), super::DeviceAdaptorError> {
-        let mut buf = buf.clone();
+    async fn send(&self, mut buf: super::Frame) -> Result<(), super::DeviceAdaptorError> {
 
         buf.expand_head(8)?;
         buf.expand_tail(1)?;
         let meta_len = buf.meta.len;
-        let meta_data_type = buf.meta.data_type;
-        let meta_command_type = buf.meta.command_type;
+        // a frame which was not received from a uart, like a request of a client, is a telecommand
+        let (meta_data_type, meta_command_type) = if buf.meta.data_type == 0 {
+            (
+                CommandType::TeleCommand as u8,
+                Command::TeleCommand(TeleCommand::BasicTeleCommand).into(),
+            )
+        } else {
+            (buf.meta.data_type, buf.meta.command_type)
+        };
         let meta_req_id = buf.meta.id;
 
         let data = &mut buf.data_mut()[0..(meta_len) as usize];
@@ -68,23 +102,39 @@ impl DeviceAdaptor for Uart {
 
         hasher.update(&data[3..data.len() - 1]);
         data[data.len() - 1] = hasher.finalize();
-        self.file.lock().await.write_all(data)?;
+        let result = self.file.lock().await.write_all(data);
+        self.stats.record_send(&result);
+        result?;
 
         Ok(())
     }
 
     async fn recv(&self) -> Result<super::Frame, super::DeviceAdaptorError> {
-        // read the data from the uart device
-        let mut buf = [0u8; 150];
-        let n = self
-            .file
-            .lock()
-            .await
-            .read(&mut buf)
-            .map_err(|_| super::DeviceAdaptorError::Empty)?;
+        // read the data from the uart device, which blocks until the timeout of the port,
+        // so it runs aside and does not hold up the other tasks
+        let reader = Arc::clone(&self.reader);
+        let (n, buf) = tokio::task::spawn_blocking(move || {
+            let mut buf = [0u8; 150];
+            let n = reader
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .read(&mut buf)?;
+            Ok::<_, io::Error>((n, buf))
+        })
+        .await
+        .map_err(io::Error::other)?
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted => {
+                DeviceAdaptorError::Empty
+            }
+            _ => e.into(),
+        })?;
         // return the data
         let ty_uart = TyUartProtocol::from_slice_to_self(&buf[0..n])
-            .map_err(|_| super::DeviceAdaptorError::FrameError("recv data error".to_string()))?
+            .map_err(|_| {
+                self.stats.parse_errors.incr();
+                super::DeviceAdaptorError::FrameError("recv data error".to_string())
+            })?
             .1;
 
         let framemeta = FrameMeta {
@@ -97,6 +147,10 @@ impl DeviceAdaptor for Uart {
             ..Default::default()
         };
         let frame = Frame::new(framemeta, &ty_uart.data);
+        match frame {
+            Ok(_) => self.stats.received.incr(),
+            Err(_) => self.stats.parse_errors.incr(),
+        }
 
         frame.map_err(|_| super::DeviceAdaptorError::FrameError("recv data error".to_string()))
     }
@@ -108,6 +162,29 @@ impl DeviceAdaptor for Uart {
             128
         }
     }
+
+    async fn flush(&self) -> Result<(), super::DeviceAdaptorError> {
+        self.file.lock().await.flush()?;
+        Ok(())
+    }
+
+    fn stats(&self) -> AdaptorStats {
+        self.stats.snapshot()
+    }
+
+    async fn reopen(&self) -> Result<(), DeviceAdaptorError> {
+        let port = open_port(&self.device_name, self.baud_rate)?;
+        let reader = port.try_clone().map_err(io::Error::from)?;
+        *self.reader.lock().unwrap_or_else(PoisonError::into_inner) = reader;
+        *self.file.lock().await = port;
+        log::info!("reopened {}", self.device_name);
+        Ok(())
+    }
+
+    /// The platform id of the framing is not a node id, the node at the other end sends every frame.
+    fn addressed(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -0,0 +1,118 @@
+use std::io;
+
+use async_trait::async_trait;
+use tokio::net::{ToSocketAddrs, UdpSocket};
+
+use super::{
+    AdaptorCounters, AdaptorStats, DeviceAdaptor, DeviceAdaptorError, Frame, FrameFlag, FrameMeta,
+};
+
+/// src_id(1B), dest_id(1B) and id(1B)
+const UDP_HEADER_SIZE: usize = 3;
+const UDP_MTU: usize = 150;
+
+/// A point to point link over UDP, to run the server and the client on hosts without a bus.
+///
+/// Every datagram carries one frame: `src_id(1B) | dest_id(1B) | id(1B) | payload`.
+/// Only datagrams from the peer are received.
+#[derive(Debug)]
+pub struct Udp {
+    socket: UdpSocket,
+    stats: AdaptorCounters,
+}
+
+impl Udp {
+    /// Bind to `local` and exchange frames with `peer`.
+    pub async fn new(local: impl ToSocketAddrs, peer: impl ToSocketAddrs) -> io::Result<Self> {
+        let socket = UdpSocket::bind(local).await?;
+        socket.connect(peer).await?;
+        Ok(Self {
+            socket,
+            stats: AdaptorCounters::default(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<std::net::SocketAddr> {
+        self.socket.local_addr()
+    }
+}
+
+#[async_trait]
+impl DeviceAdaptor for Udp {
+    async fn send(&self, frame: Frame) -> Result<(), DeviceAdaptorError> {
+        let mut datagram = Vec::with_capacity(UDP_HEADER_SIZE + frame.len());
+        datagram.extend_from_slice(&[frame.meta.src_id, frame.meta.dest_id, frame.meta.id]);
+        datagram.extend_from_slice(frame.data());
+        let result = self.socket.send(&datagram).await;
+        self.stats.record_send(&result);
+        result?;
+        Ok(())
+    }
+
+    async fn recv(&self) -> Result<Frame, DeviceAdaptorError> {
+        let mut buf = [0u8; UDP_HEADER_SIZE + UDP_MTU];
+        let n = self.socket.recv(&mut buf).await?;
+        let Some((header, payload)) = buf[..n].split_first_chunk::<UDP_HEADER_SIZE>() else {
+            self.stats.parse_errors.incr();
+            return Err(DeviceAdaptorError::FrameError(format!(
+                "datagram of {} bytes too short",
+                n
+            )));
+        };
+        let meta = FrameMeta {
+            src_id: header[0],
+            dest_id: header[1],
+            id: header[2],
+            ..Default::default()
+        };
+        let frame = Frame::new(meta, payload).map_err(|e| {
+            self.stats.parse_errors.incr();
+            DeviceAdaptorError::FrameError(e.to_string())
+        })?;
+        self.stats.received.incr();
+        Ok(frame)
+    }
+
+    fn mtu(&self, _flag: FrameFlag) -> usize {
+        UDP_MTU
+    }
+
+    fn stats(&self) -> AdaptorStats {
+        self.stats.snapshot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeviceAdaptor, Frame, FrameMeta, Udp};
+
+    #[tokio::test]
+    async fn test_udp_link() {
+        let server = Udp::new("127.0.0.1:0", "127.0.0.1:9").await.unwrap();
+        let client = Udp::new("127.0.0.1:0", server.local_addr().unwrap())
+            .await
+            .unwrap();
+        server
+            .socket
+            .connect(client.local_addr().unwrap())
+            .await
+            .unwrap();
+
+        let meta = FrameMeta {
+            src_id: 0,
+            dest_id: 0x2a,
+            id: 7,
+            ..Default::default()
+        };
+        client
+            .send(Frame::new(meta, &[1, 2, 3]).unwrap())
+            .await
+            .unwrap();
+        let frame = server.recv().await.unwrap();
+        assert_eq!(frame.meta.dest_id, 0x2a);
+        assert_eq!(frame.meta.id, 7);
+        assert_eq!(frame.data(), [1, 2, 3]);
+        assert_eq!(server.stats().received, 1);
+        assert_eq!(client.stats().sent, 1);
+    }
+}
@@ -0,0 +1,243 @@
+use std::{
+    io,
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+
+use crate::adaptor::{AdaptorStats, FrameMeta};
+use crate::server::{Downlink, ServerStats};
+
+use super::{Application, Frame, Response};
+use crate::client::{Answer, ApplicationSpec, Decoded, OperationSpec};
+
+/// Reports the counters of the server and its adaptors, so the ground can read them during a pass.
+///
+/// A request is answered with one frame of server counters, followed by one frame per adaptor.
+/// Every counter is a big endian `u32`, saturated at `u32::MAX`.
+///
+/// The server frame holds, in order: received, rate limited, dropped, malformed, version mismatches,
+/// unknown applications, permission denied, handled, handler errors and timeouts.
+///
+/// An adaptor frame starts with the adaptor index as one byte, then holds: received, sent,
+/// send errors, parse errors, checksum errors and slot overflows.
+#[derive(Default)]
+pub struct Diagnostics {
+    downlink: Mutex<Option<Downlink>>,
+}
+
+#[async_trait]
+impl Application for Diagnostics {
+    async fn handle(&self, frame: Frame, _mtu: u16) -> io::Result<Response> {
+        let downlink = self
+            .downlink
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "not initialized"))?;
+        let stats = downlink.stats()?;
+        let mut frames = vec![response(frame.meta(), &encode_server(&stats))?];
+        for (index, adaptor) in stats.adaptors.iter().enumerate() {
+            let index = u8::try_from(index)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "too many adaptors"))?;
+            frames.push(response(frame.meta(), &encode_adaptor(index, adaptor))?);
+        }
+        Ok(Response::Frames(frames))
+    }
+
+    fn application_id(&self) -> u8 {
+        Self::APPLICATION_ID
+    }
+
+    fn application_name(&self) -> &'static str {
+        "Diagnostics"
+    }
+
+    async fn init(&self, downlink: Downlink) -> io::Result<()> {
+        *self.downlink.lock().unwrap_or_else(PoisonError::into_inner) = Some(downlink);
+        Ok(())
+    }
+}
+
+impl Diagnostics {
+    pub const APPLICATION_ID: u8 = 7;
+    /// How long a client waits for the next frame of a response, unless the server paces them slower.
+    pub const RESPONSE_IDLE: Duration = Duration::from_millis(200);
+    pub const SPEC: ApplicationSpec = ApplicationSpec {
+        name: "diagnostics",
+        id: Self::APPLICATION_ID,
+        about: "Print the counters of the node and of its adaptors.",
+        operations: &[OperationSpec {
+            name: None,
+            about: "",
+            arguments: &[],
+            answer: Answer::Frames(Self::RESPONSE_IDLE),
+            request: |_| Ok(vec![Self::request()]),
+            parse: |_, frames| Ok(decoded(&Self::parse_response(frames)?)),
+        }],
+    };
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request() -> Frame {
+        Frame::new(Self::APPLICATION_ID)
+    }
+
+    /// Decode the frames of a response, the server frame first, into the counters they hold.
+    pub fn parse_response(frames: &[Frame]) -> io::Result<ServerStats> {
+        let Some((server, adaptors)) = frames.split_first() else {
+            return Err(invalid("empty diagnostics response"));
+        };
+        let mut stats = decode_server(server.data())?;
+        for frame in adaptors {
+            let (index, adaptor) = decode_adaptor(frame.data())?;
+            if stats.adaptors.len() <= index {
+                stats.adaptors.resize(index + 1, AdaptorStats::default());
+            }
+            stats.adaptors[index] = adaptor;
+        }
+        Ok(stats)
+    }
+}
+
+fn response(request: &FrameMeta, payload: &[u8]) -> io::Result<Frame> {
+    let mut frame = Frame::new(Diagnostics::APPLICATION_ID);
+    frame.set_meta_from_request(request);
+    frame.set_len(payload.len() as u16)?;
+    frame.data_mut().copy_from_slice(payload);
+    Ok(frame)
+}
+
+fn encode_server(stats: &ServerStats) -> Vec<u8> {
+    encode_counters(
+        Vec::new(),
+        [
+            stats.received,
+            stats.rate_limited,
+            stats.dropped,
+            stats.malformed,
+            stats.version_mismatches,
+            stats.unknown_applications,
+            stats.permission_denied,
+            stats.handled,
+            stats.handler_errors,
+            stats.timeouts,
+        ],
+    )
+}
+
+fn encode_adaptor(index: u8, stats: &AdaptorStats) -> Vec<u8> {
+    encode_counters(
+        vec![index],
+        [
+            stats.received,
+            stats.sent,
+            stats.send_errors,
+            stats.parse_errors,
+            stats.checksum_errors,
+            stats.slot_overflows,
+        ],
+    )
+}
+
+fn encode_counters(mut buf: Vec<u8>, counters: impl IntoIterator<Item = u64>) -> Vec<u8> {
+    for counter in counters {
+        let counter = u32::try_from(counter).unwrap_or(u32::MAX);
+        buf.extend_from_slice(&counter.to_be_bytes());
+    }
+    buf
+}
+
+fn decode_server(data: &[u8]) -> io::Result<ServerStats> {
+    let mut counters = decode_counters(data, 10)?;
+    let mut next = || counters.next().unwrap_or_default();
+    // in the order of `encode_server`, as fields are initialized in order
+    Ok(ServerStats {
+        received: next(),
+        rate_limited: next(),
+        dropped: next(),
+        malformed: next(),
+        version_mismatches: next(),
+        unknown_applications: next(),
+        permission_denied: next(),
+        handled: next(),
+        handler_errors: next(),
+        timeouts: next(),
+        adaptors: Vec::new(),
+    })
+}
+
+/// The index of the adaptor and its counters.
+fn decode_adaptor(data: &[u8]) -> io::Result<(usize, AdaptorStats)> {
+    let Some((&index, data)) = data.split_first() else {
+        return Err(invalid("empty adaptor counters"));
+    };
+    let mut counters = decode_counters(data, 6)?;
+    let mut next = || counters.next().unwrap_or_default();
+    let stats = AdaptorStats {
+        received: next(),
+        sent: next(),
+        send_errors: next(),
+        parse_errors: next(),
+        checksum_errors: next(),
+        slot_overflows: next(),
+    };
+    Ok((usize::from(index), stats))
+}
+
+/// The `count` counters encoded by `encode_counters`.
+fn decode_counters(data: &[u8], count: usize) -> io::Result<impl Iterator<Item = u64> + '_> {
+    if data.len() != count * 4 {
+        return Err(invalid("unexpected length of counters"));
+    }
+    Ok(data
+        .chunks_exact(4)
+        .map(|chunk| u64::from(u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))))
+}
+
+/// The counters of `stats`, as the client displays them.
+fn decoded(stats: &ServerStats) -> Decoded {
+    let adaptors = stats
+        .adaptors
+        .iter()
+        .map(|adaptor| {
+            Decoded::Record(vec![
+                ("received", Decoded::Number(adaptor.received)),
+                ("sent", Decoded::Number(adaptor.sent)),
+                ("send_errors", Decoded::Number(adaptor.send_errors)),
+                ("parse_errors", Decoded::Number(adaptor.parse_errors)),
+                ("checksum_errors", Decoded::Number(adaptor.checksum_errors)),
+                ("slot_overflows", Decoded::Number(adaptor.slot_overflows)),
+            ])
+        })
+        .collect();
+    Decoded::Record(vec![
+        ("received", Decoded::Number(stats.received)),
+        ("rate_limited", Decoded::Number(stats.rate_limited)),
+        ("dropped", Decoded::Number(stats.dropped)),
+        ("malformed", Decoded::Number(stats.malformed)),
+        (
+            "version_mismatches",
+            Decoded::Number(stats.version_mismatches),
+        ),
+        (
+            "unknown_applications",
+            Decoded::Number(stats.unknown_applications),
+        ),
+        (
+            "permission_denied",
+            Decoded::Number(stats.permission_denied),
+        ),
+        ("handled", Decoded::Number(stats.handled)),
+        ("handler_errors", Decoded::Number(stats.handler_errors)),
+        ("timeouts", Decoded::Number(stats.timeouts)),
+        ("adaptors", Decoded::List(adaptors)),
+    ])
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use tokio::sync::Mutex;
 
-use super::{Application, Fallback, Frame};
+use super::{Application, Fallback, Frame, Response};
 
 pub struct DownloadCommand<F> {
     state: Mutex<Box<DownloadState>>,
@@ -17,7 +17,7 @@ enum DownloadState {
 
 #[async_trait]
 impl<F: Fallback> Application for DownloadCommand<F> {
-    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Option<Frame>> {
+    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
         let mut guard = self.state.lock().await;
         let state = guard.as_mut();
 
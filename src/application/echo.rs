@@ -1,18 +1,19 @@
-use std::io;
-
 use async_trait::async_trait;
 
-use super::{Application, Frame};
+use super::{Application, Frame, Response};
+use crate::client::{
+    bytes, single, Answer, ApplicationSpec, ArgumentKind, ArgumentSpec, Decoded, OperationSpec,
+};
 
 pub struct EchoCommand;
 
 #[async_trait]
 impl Application for EchoCommand {
-    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Option<Frame>> {
+    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
         let mut response = Frame::new_from_slice(Self::APPLICATION_ID, frame.data())?;
         response.set_meta_from_request(frame.meta());
         
-        Ok(Some(response))
+        Ok(Response::Single(response))
     }
 
     fn application_id(&self) -> u8 {
@@ -25,14 +26,27 @@ impl Application for EchoCommand {
 }
 
 impl EchoCommand {
-    pub(crate) const APPLICATION_ID: u8 = 2;
-    pub(crate) fn request(&self, mtu: u16, content: &[u8]) -> std::io::Result<Frame> {
-        if content.len() > mtu.into() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "too long content",
-            ));
-        }
-        Frame::new_from_slice(2, content)
+    pub const APPLICATION_ID: u8 = 2;
+    pub const SPEC: ApplicationSpec = ApplicationSpec {
+        name: "echo",
+        id: Self::APPLICATION_ID,
+        about: "Send a text, or bytes with --hex, and print what the node echoes.",
+        operations: &[OperationSpec {
+            name: None,
+            about: "",
+            arguments: &[ArgumentSpec {
+                name: "content",
+                help: "The content to echo.",
+                kind: ArgumentKind::Content,
+            }],
+            answer: Answer::Single,
+            request: |arguments| Ok(vec![Self::request(bytes(arguments, 0)?)?]),
+            parse: |_, frames| Ok(Decoded::Bytes(single(frames)?.data().to_vec())),
+        }],
+    };
+
+    /// A request echoing `content`, which fits in one frame.
+    pub fn request(content: &[u8]) -> std::io::Result<Frame> {
+        Frame::new_from_slice(Self::APPLICATION_ID, content)
     }
 }
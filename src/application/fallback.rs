@@ -1,9 +1,12 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use tokio::sync::Mutex;
 use zeromq::{Socket, SocketRecv, SocketSend, ZmqResult};
 
+/// How long the applications forwarding to a fallback wait for its reply by default.
+pub(crate) const FALLBACK_TIMEOUT: Duration = Duration::from_millis(100);
+
 #[async_trait]
 pub trait Fallback: Send + Sync {
     /// Provide a fallback mechanism for the application
@@ -1,7 +1,9 @@
+mod diagnostics;
 mod download;
 mod echo;
 mod reboot;
 mod reset_network;
+mod response;
 mod telemetry;
 mod time_sync;
 mod udp_backup;
@@ -10,29 +12,133 @@ mod upload;
 /// The fallback is an adpter to the restrive adta or send data to the
 mod fallback;
 
+use std::time::Duration;
+
 use async_trait::async_trait;
+pub use diagnostics::Diagnostics;
 pub use echo::EchoCommand;
 pub use fallback::{Fallback, ZeromqSocket};
+use fallback::FALLBACK_TIMEOUT;
 pub use reboot::Reboot;
 pub use telemetry::TeleMetry;
 pub use time_sync::TimeSync;
 pub use udp_backup::UdpBackup;
-pub use reset_network::ResetNetwork;
+pub use reset_network::{NetworkFlag, NetworkInterfaceStatus, NetworkStatus, ResetNetwork};
+pub use response::Response;
 
 #[cfg(test)]
 pub(crate) use fallback::DummyFallback;
 
 use crate::protocol::Frame;
+use crate::server::Downlink;
 
+/// Answers the requests to its id. A server runs the built-in applications and any other like:
+///
+/// ```
+/// use async_trait::async_trait;
+/// use tcsp::{Application, Frame, Response};
+///
+/// struct Ping;
+///
+/// #[async_trait]
+/// impl Application for Ping {
+///     async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
+///         let mut response = Frame::new_from_slice(self.application_id(), frame.data())?;
+///         response.set_meta_from_request(frame.meta());
+///         Ok(Response::Single(response))
+///     }
+///
+///     fn application_id(&self) -> u8 {
+///         42
+///     }
+///
+///     fn application_name(&self) -> &'static str {
+///         "Ping"
+///     }
+/// }
+/// ```
 #[async_trait]
 pub trait Application: Send + Sync {
     /// TODO: what if the frame is very large? Start a new thread?
     /// Parse the bus frame into an application frame
-    async fn handle(&self, frame: Frame, mtu: u16) -> std::io::Result<Option<Frame>>;
+    ///
+    /// `mtu` is the largest payload of one response frame. Answer with several frames if it is not enough.
+    async fn handle(&self, frame: Frame, mtu: u16) -> std::io::Result<Response>;
 
     fn application_id(&self) -> u8;
 
     fn application_name(&self) -> &'static str;
 
-    async fn init(&self) {}
+    /// How long a request is handled at most when the application is registered without
+    /// `ApplicationOptions::timeout`. By default a handler is not bounded.
+    fn default_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Called by the server before the first frame is accepted.
+    /// Returning an error aborts the startup of the server.
+    ///
+    /// Keep `downlink` to send frames which do not answer a request.
+    async fn init(&self, _downlink: Downlink) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// Called by the server when it stops, after the in-flight requests are finished.
+    async fn shutdown(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// Report whether the application is able to serve requests.
+    async fn health(&self) -> Health {
+        Health::Healthy
+    }
+}
+
+/// The health of an application, reported by `Application::health`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Health {
+    Healthy,
+    /// The application serves requests, but something is wrong.
+    Degraded(String),
+    /// The application can not serve requests.
+    Unhealthy(String),
+}
+
+impl Health {
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, Health::Healthy)
+    }
+
+    fn severity(&self) -> u8 {
+        match self {
+            Health::Healthy => 0,
+            Health::Degraded(_) => 1,
+            Health::Unhealthy(_) => 2,
+        }
+    }
+}
+
+/// The health of every application registered in a server.
+#[derive(Debug, Clone, Default)]
+pub struct HealthReport {
+    pub applications: Vec<ApplicationHealth>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApplicationHealth {
+    pub id: u8,
+    pub name: &'static str,
+    pub health: Health,
+}
+
+impl HealthReport {
+    /// The worst health among all applications.
+    pub fn overall(&self) -> Health {
+        self.applications
+            .iter()
+            .map(|application| &application.health)
+            .max_by_key(|health| health.severity())
+            .cloned()
+            .unwrap_or(Health::Healthy)
+    }
 }
@@ -1,17 +1,25 @@
+use std::io;
+
 use async_trait::async_trait;
 
-use super::{Application, Frame};
+use super::{Application, Frame, Response};
+use crate::client::{single, status, Answer, ApplicationSpec, OperationSpec};
+
+const REBOOT_ACK: &[u8] = b"ok";
 
 pub struct Reboot {}
 
 #[async_trait]
 impl Application for Reboot {
-    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Option<Frame>> {
-        let mut response = Frame::new_from_slice(Self::APPLICATION_ID, "ok".as_bytes())?;
+    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
+        let mut response = Frame::new(Self::APPLICATION_ID);
+        // the meta of the request carries its length, set the length after it
         response.set_meta_from_request(frame.meta());
+        response.set_len(REBOOT_ACK.len() as u16)?;
+        response.data_mut().copy_from_slice(REBOOT_ACK);
 
         log::info!("receive reboot");
-        Ok(Some(response))
+        Ok(Response::Single(response))
     }
 
     fn application_id(&self) -> u8 {
@@ -24,8 +32,36 @@ impl Application for Reboot {
 }
 
 impl Reboot {
-    pub(crate) const APPLICATION_ID: u8 = 3;
-    pub(crate) fn request(&self) -> std::io::Result<Frame> {
-        Ok(Frame::new(Self::APPLICATION_ID))
+    pub const APPLICATION_ID: u8 = 3;
+    pub const SPEC: ApplicationSpec = ApplicationSpec {
+        name: "reboot",
+        id: Self::APPLICATION_ID,
+        about: "Reboot the node.",
+        operations: &[OperationSpec {
+            name: None,
+            about: "",
+            arguments: &[],
+            answer: Answer::Single,
+            request: |_| Ok(vec![Self::request()]),
+            parse: |_, frames| {
+                Self::parse_response(single(frames)?)?;
+                Ok(status("rebooting"))
+            },
+        }],
+    };
+
+    pub fn request() -> Frame {
+        Frame::new(Self::APPLICATION_ID)
+    }
+
+    /// Check the node acknowledged the reboot.
+    pub fn parse_response(frame: &Frame) -> io::Result<()> {
+        if frame.data() != REBOOT_ACK {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected reboot response {:?}", frame.data()),
+            ));
+        }
+        Ok(())
     }
 }
@@ -1,9 +1,9 @@
 use std::{
     error::Error,
+    io,
     mem::size_of,
     net::Ipv4Addr,
     process::Stdio,
-    slice::{from_raw_parts, from_raw_parts_mut},
     str::FromStr,
 };
 
@@ -11,7 +11,8 @@ use async_trait::async_trait;
 use bitflags::bitflags;
 use tokio::process::Command;
 
-use super::{Application, Frame};
+use super::{Application, Frame, Response};
+use crate::client::{single, status, Answer, ApplicationSpec, Decoded, OperationSpec};
 
 pub struct ResetNetwork;
 
@@ -45,7 +46,7 @@ enum NetworkControlCommand {
 }
 
 #[repr(u8)]
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 enum NetworkControlStatus {
     _Unknown = 0,
 
@@ -54,6 +55,17 @@ enum NetworkControlStatus {
     UnknowCommand = 3,
 }
 
+impl From<u8> for NetworkControlStatus {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => NetworkControlStatus::Ok,
+            2 => NetworkControlStatus::RunError,
+            3 => NetworkControlStatus::UnknowCommand,
+            _ => NetworkControlStatus::_Unknown,
+        }
+    }
+}
+
 impl From<u8> for NetworkControlCommand {
     fn from(value: u8) -> Self {
         match value {
@@ -66,11 +78,14 @@ impl From<u8> for NetworkControlCommand {
 
 #[async_trait]
 impl Application for ResetNetwork {
-    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Option<Frame>> {
+    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
         let mut response = Frame::new_from_slice(Self::APPLICATION_ID, frame.data())?;
         response.set_meta_from_request(frame.meta());
 
-        let cmd = NetworkControlCommand::from(frame.data()[0]);
+        let header = frame.data().first().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "missing the network command")
+        })?;
+        let cmd = NetworkControlCommand::from(*header);
         match cmd {
             NetworkControlCommand::List => {
                 response.set_len(
@@ -78,7 +93,7 @@ impl Application for ResetNetwork {
                 )?;
                 response.data_mut()[size_of::<NetworkControlHeader>()
                     ..size_of::<NetworkControlHeader>() + size_of::<NetworkStatus>()]
-                    .copy_from_slice(list_status().await.into_network_endian_buffer());
+                    .copy_from_slice(&list_status().await.to_network_endian_buffer());
                 response.data_mut()[0] = make_header(cmd, NetworkControlStatus::Ok).into();
                 log::debug!("receive net interface list. Response:{:?}", response);
             }
@@ -102,7 +117,7 @@ impl Application for ResetNetwork {
             }
         }
 
-        Ok(Some(response))
+        Ok(Response::Single(response))
     }
 
     fn application_id(&self) -> u8 {
@@ -112,8 +127,6 @@ impl Application for ResetNetwork {
     fn application_name(&self) -> &'static str{
         "Reset Network"
     }
-
-    async fn init(&self) {}
 }
 bitflags! {
     #[derive(Debug, Clone,Copy,Default)]
@@ -131,19 +144,6 @@ bitflags! {
     }
 }
 
-impl NetworkFlag {
-    fn as_network_nedian(&mut self) {
-        #[cfg(target_endian = "little")]
-        {
-            *self = Self::from_bits_truncate(self.bits().to_be())
-        }
-        #[cfg(target_endian = "big")]
-        {
-            *self = Self::from_bits_truncate(self.bits().to_le())
-        }
-    }
-}
-
 async fn reset_all_network() -> bool {
     let output = Command::new("netplan")
         .arg("apply")
@@ -157,11 +157,12 @@ async fn reset_all_network() -> bool {
 const _MUST_NO_EXCEED_MTU: () =
     assert!(size_of::<NetworkControlHeader>() + size_of::<NetworkStatus>() < 100);
 
+/// The address and the flags of a network interface, as `ifconfig` reports them.
 #[repr(C)]
-#[derive(Debug)]
-struct NetworkInterfaceStatus {
-    ip: Ipv4Addr,
-    state: NetworkFlag,
+#[derive(Debug, Clone)]
+pub struct NetworkInterfaceStatus {
+    pub ip: Ipv4Addr,
+    pub state: NetworkFlag,
     _reserve: [u8; 24],
 }
 
@@ -174,11 +175,12 @@ impl Default for NetworkInterfaceStatus {
         }
     }
 }
+/// The network interfaces of a node, answered to `ResetNetwork::list_request`.
 #[repr(C)]
-#[derive(Debug, Default)]
-struct NetworkStatus {
-    eth0: NetworkInterfaceStatus,
-    eth1: NetworkInterfaceStatus,
+#[derive(Debug, Clone, Default)]
+pub struct NetworkStatus {
+    pub eth0: NetworkInterfaceStatus,
+    pub eth1: NetworkInterfaceStatus,
 }
 
 impl NetworkStatus {
@@ -191,11 +193,52 @@ impl NetworkStatus {
         unsafe { Some(&mut *ptr) }
     }
 
-    fn into_network_endian_buffer(mut self) -> &'static [u8] {
-        self.eth0.state.as_network_nedian();
-        self.eth1.state.as_network_nedian();
-        let ptr = &self as *const NetworkStatus as *const u8;
-        unsafe { from_raw_parts(ptr, size_of::<Self>()) }
+    /// The interfaces, as the client displays them.
+    fn decoded(&self) -> Decoded {
+        let interface = |interface: &NetworkInterfaceStatus| {
+            let flags = interface
+                .state
+                .iter_names()
+                .map(|(name, _)| Decoded::Text(name.to_owned()))
+                .collect();
+            Decoded::Record(vec![
+                ("ip", Decoded::Text(interface.ip.to_string())),
+                ("flags", Decoded::List(flags)),
+            ])
+        };
+        Decoded::Record(vec![
+            ("eth0", interface(&self.eth0)),
+            ("eth1", interface(&self.eth1)),
+        ])
+    }
+
+    /// Parse the status written by `to_network_endian_buffer`.
+    fn from_network_endian_buffer(buf: &[u8]) -> Option<Self> {
+        let interface = |bytes: &[u8]| {
+            let ip: [u8; 4] = bytes.get(..4)?.try_into().ok()?;
+            let state: [u8; 4] = bytes.get(4..8)?.try_into().ok()?;
+            Some(NetworkInterfaceStatus {
+                ip: Ipv4Addr::from(ip),
+                state: NetworkFlag::from_bits_retain(u32::from_be_bytes(state)),
+                _reserve: Default::default(),
+            })
+        };
+        let len = size_of::<NetworkInterfaceStatus>();
+        Some(Self {
+            eth0: interface(buf.get(..len)?)?,
+            eth1: interface(buf.get(len..2 * len)?)?,
+        })
+    }
+
+    /// The layout of `NetworkStatus`, with the flags in network endian.
+    fn to_network_endian_buffer(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(size_of::<Self>());
+        for interface in [&self.eth0, &self.eth1] {
+            buf.extend_from_slice(&interface.ip.octets());
+            buf.extend_from_slice(&interface.state.bits().to_be_bytes());
+            buf.extend_from_slice(&interface._reserve);
+        }
+        buf
     }
 }
 impl From<&str> for NetworkFlag {
@@ -271,5 +314,92 @@ fn extract_ipv4(input: &str) -> Option<Ipv4Addr> {
 }
 
 impl ResetNetwork {
-    pub(crate) const APPLICATION_ID: u8 = 5;
+    pub const APPLICATION_ID: u8 = 5;
+    pub const SPEC: ApplicationSpec = ApplicationSpec {
+        name: "reset-network",
+        id: Self::APPLICATION_ID,
+        about: "Inspect or reset the network of the node.",
+        operations: &[
+            OperationSpec {
+                name: Some("list"),
+                about: "Print the address and the flags of the network interfaces.",
+                arguments: &[],
+                answer: Answer::Single,
+                request: |_| Ok(vec![Self::list_request()?]),
+                parse: |_, frames| Ok(Self::parse_list_response(single(frames)?)?.decoded()),
+            },
+            OperationSpec {
+                name: Some("reset"),
+                about: "Reapply the network configuration.",
+                arguments: &[],
+                answer: Answer::Single,
+                request: |_| Ok(vec![Self::reset_request()?]),
+                parse: |_, frames| {
+                    Self::parse_reset_response(single(frames)?)?;
+                    Ok(status("reset"))
+                },
+            },
+        ],
+    };
+
+    /// A request for the status of the network interfaces, parsed by `parse_list_response`.
+    pub fn list_request() -> io::Result<Frame> {
+        Frame::new_from_slice(Self::APPLICATION_ID, &[NetworkControlCommand::List as u8])
+    }
+
+    /// A request to reapply the network configuration, parsed by `parse_reset_response`.
+    pub fn reset_request() -> io::Result<Frame> {
+        Frame::new_from_slice(Self::APPLICATION_ID, &[NetworkControlCommand::ResetAll as u8])
+    }
+
+    pub fn parse_list_response(frame: &Frame) -> io::Result<NetworkStatus> {
+        let data = Self::check_status(frame)?;
+        NetworkStatus::from_network_endian_buffer(data).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "network status too short")
+        })
+    }
+
+    pub fn parse_reset_response(frame: &Frame) -> io::Result<()> {
+        Self::check_status(frame).map(|_| ())
+    }
+
+    /// Check the status in the header of a response, and return the payload after it.
+    fn check_status(frame: &Frame) -> io::Result<&[u8]> {
+        let Some((&header, data)) = frame.data().split_first() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "empty network control response",
+            ));
+        };
+        match NetworkControlStatus::from(header >> 6) {
+            NetworkControlStatus::Ok => Ok(data),
+            status => Err(io::Error::other(format!(
+                "network control failed: {:?}",
+                status
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{mem::size_of, net::Ipv4Addr};
+
+    use super::{NetworkFlag, NetworkStatus};
+
+    #[test]
+    fn test_network_status_buffer() {
+        let mut status = NetworkStatus::default();
+        status.eth1.ip = Ipv4Addr::new(192, 168, 1, 2);
+        status.eth1.state = NetworkFlag::UP | NetworkFlag::RUNNING;
+        let buf = status.to_network_endian_buffer();
+        assert_eq!(buf.len(), size_of::<NetworkStatus>());
+        assert_eq!(buf[32..40], [192, 168, 1, 2, 0, 0, 0, 0b1001]);
+
+        let parsed = NetworkStatus::from_network_endian_buffer(&buf).unwrap();
+        assert_eq!(parsed.eth0.ip, Ipv4Addr::UNSPECIFIED);
+        assert_eq!(parsed.eth1.ip, status.eth1.ip);
+        assert_eq!(parsed.eth1.state.bits(), status.eth1.state.bits());
+        assert!(NetworkStatus::from_network_endian_buffer(&buf[..40]).is_none());
+    }
 }
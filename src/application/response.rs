@@ -0,0 +1,73 @@
+use std::{fmt, io};
+
+use futures_util::{stream::BoxStream, Stream, StreamExt};
+
+use crate::protocol::Frame;
+
+/// What an application answers to a request.
+///
+/// An answer larger than the `mtu` is split into several frames, which the server sends in order.
+/// Set `ApplicationOptions::pacing` to leave a gap between them on a slow link.
+#[derive(Default)]
+pub enum Response {
+    /// The request is not answered.
+    #[default]
+    Empty,
+    Single(Frame),
+    Frames(Vec<Frame>),
+    /// Frames produced while the previous ones are sent. An error ends the response.
+    Stream(BoxStream<'static, io::Result<Frame>>),
+}
+
+impl Response {
+    pub fn stream(stream: impl Stream<Item = io::Result<Frame>> + Send + 'static) -> Self {
+        Self::Stream(stream.boxed())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Empty => true,
+            Self::Frames(frames) => frames.is_empty(),
+            Self::Single(_) | Self::Stream(_) => false,
+        }
+    }
+
+    /// Turn the response into a stream of frames.
+    pub(crate) fn into_stream(self) -> BoxStream<'static, io::Result<Frame>> {
+        match self {
+            Self::Empty => futures_util::stream::empty().boxed(),
+            Self::Single(frame) => futures_util::stream::once(async { Ok(frame) }).boxed(),
+            Self::Frames(frames) => futures_util::stream::iter(frames.into_iter().map(Ok)).boxed(),
+            Self::Stream(stream) => stream,
+        }
+    }
+}
+
+impl From<Frame> for Response {
+    fn from(frame: Frame) -> Self {
+        Self::Single(frame)
+    }
+}
+
+impl From<Option<Frame>> for Response {
+    fn from(frame: Option<Frame>) -> Self {
+        frame.map_or(Self::Empty, Self::Single)
+    }
+}
+
+impl From<Vec<Frame>> for Response {
+    fn from(frames: Vec<Frame>) -> Self {
+        Self::Frames(frames)
+    }
+}
+
+impl fmt::Debug for Response {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "Empty"),
+            Self::Single(frame) => f.debug_tuple("Single").field(frame).finish(),
+            Self::Frames(frames) => write!(f, "Frames({} frames)", frames.len()),
+            Self::Stream(_) => write!(f, "Stream"),
+        }
+    }
+}
@@ -1,9 +1,9 @@
 use std::time::Duration;
 
 use async_trait::async_trait;
-use tokio::time::timeout;
 
-use super::{Application, Fallback, Frame};
+use super::{Application, Fallback, Frame, Response, FALLBACK_TIMEOUT};
+use crate::client::{single, Answer, ApplicationSpec, Decoded, OperationSpec};
 
 pub struct TeleMetry<F> {
     fallback: F,
@@ -11,19 +11,19 @@ pub struct TeleMetry<F> {
 
 #[async_trait]
 impl<F: Fallback> Application for TeleMetry<F> {
-    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Option<Frame>> {
+    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
         let mut response = Frame::new(Self::APPLICATION_ID);
         response.set_meta_from_request(frame.meta());
         response.set_len(100)?;
         const TELEMETRY_CODE :  [u8;4]= [0,0,0xea,0x60];
         let send_future = self.fallback.fallback(TELEMETRY_CODE.to_vec());
-        let reply = timeout(Duration::from_millis(100), send_future).await??;
+        let reply = send_future.await?;
         let buf = response.data_mut();
 
         for (i, byte) in (0..100).zip(reply) {
             buf[i] = byte;
         }
-        Ok(Some(response))
+        Ok(Response::Single(response))
     }
 
     fn application_id(&self) -> u8 {
@@ -33,16 +33,30 @@ impl<F: Fallback> Application for TeleMetry<F> {
     fn application_name(&self) -> &'static str{
         "Telemetry"
     }
-}
 
-impl<F: Fallback> TeleMetry<F> {
-    pub(crate) const APPLICATION_ID: u8 = 0;
+    fn default_timeout(&self) -> Option<Duration> {
+        Some(FALLBACK_TIMEOUT)
+    }
 }
 
-
 impl<F> TeleMetry<F> {
+    pub const APPLICATION_ID: u8 = 0;
+    pub const SPEC: ApplicationSpec = ApplicationSpec {
+        name: "telemetry",
+        id: Self::APPLICATION_ID,
+        about: "Print the telemetry of the node.",
+        operations: &[OperationSpec {
+            name: None,
+            about: "",
+            arguments: &[],
+            answer: Answer::Single,
+            request: |_| Ok(vec![Self::request(0, 0)?]),
+            parse: |_, frames| Ok(Decoded::Bytes(single(frames)?.data().to_vec())),
+        }],
+    };
+
     pub fn request(src_id: u8, dst_id: u8) -> std::io::Result<Frame> {
-        let mut frame = Frame::new(0);
+        let mut frame = Frame::new(Self::APPLICATION_ID);
         frame.meta_mut().src_id = src_id;
         frame.meta_mut().dest_id = dst_id;
         Ok(frame)
@@ -3,9 +3,11 @@ use std::time::Duration;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use futures_util::io;
-use tokio::time::timeout;
 
-use super::{Application, Fallback, Frame};
+use super::{Application, Fallback, Frame, Response, FALLBACK_TIMEOUT};
+use crate::client::{
+    time, Answer, ApplicationSpec, ArgumentKind, ArgumentSpec, Decoded, OperationSpec,
+};
 
 pub struct TimeSync<F> {
     fallback: F,
@@ -13,13 +15,17 @@ pub struct TimeSync<F> {
 
 #[async_trait]
 impl<F: Fallback> Application for TimeSync<F> {
-    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Option<Frame>> {
-        let time_slice: [u8; 4] = frame.data()[..4].try_into().map_err(|_| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Can not convert time slice to [u8;4]",
-            )
-        })?;
+    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
+        let time_slice: [u8; 4] = frame
+            .data()
+            .get(..4)
+            .and_then(|slice| slice.try_into().ok())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Can not convert time slice to [u8;4]",
+                )
+            })?;
         let future_to_wait = self.fallback.fallback(vec![
             // 0x00,
             // 0x00,
@@ -33,7 +39,7 @@ impl<F: Fallback> Application for TimeSync<F> {
             0,
         ]);
         // we ignore the reply
-        let _reply = timeout(Duration::from_millis(100), future_to_wait).await??;
+        let _reply = future_to_wait.await?;
         let timestamp = u32::from_be_bytes(time_slice);
         let datetime = DateTime::from_timestamp(timestamp as i64, 0).ok_or_else(|| {
             io::Error::new(
@@ -42,7 +48,7 @@ impl<F: Fallback> Application for TimeSync<F> {
             )
         })?;
         log::debug!("datetime = {}", datetime);
-        Ok(None)
+        Ok(Response::Empty)
     }
 
     fn application_id(&self) -> u8 {
@@ -52,19 +58,50 @@ impl<F: Fallback> Application for TimeSync<F> {
     fn application_name(&self) -> &'static str{
         "Time synchronize"
     }
+
+    fn default_timeout(&self) -> Option<Duration> {
+        Some(FALLBACK_TIMEOUT)
+    }
 }
 
 impl<F> TimeSync<F> {
-    pub(crate) const APPLICATION_ID: u8 = 1;
+    pub const APPLICATION_ID: u8 = 1;
+    pub const SPEC: ApplicationSpec = ApplicationSpec {
+        name: "time-sync",
+        id: Self::APPLICATION_ID,
+        about: "Set the clock of the node, to now or to an RFC 3339 time.",
+        operations: &[OperationSpec {
+            name: None,
+            about: "",
+            arguments: &[ArgumentSpec {
+                name: "time",
+                help: "The time to set, now by default.",
+                kind: ArgumentKind::Time,
+            }],
+            answer: Answer::None,
+            request: |arguments| Ok(vec![Self::request(time(arguments, 0)?)?]),
+            parse: |arguments, _| {
+                let time = time(arguments, 0)?.to_rfc3339();
+                Ok(Decoded::Record(vec![("time", Decoded::Text(time))]))
+            },
+        }],
+    };
 
     /// Create a new TimeSync request frame
     ///
-    /// Provide a datetime to be used as the timestamp
-    pub(crate) fn request(datetime: DateTime<Utc>) -> std::io::Result<Frame> {
-        Frame::new_from_slice(1, &datetime.timestamp().to_be_bytes())
+    /// Provide a datetime to be used as the timestamp, which is sent as seconds in a be32.
+    /// The time sync is not answered.
+    pub fn request(datetime: DateTime<Utc>) -> std::io::Result<Frame> {
+        let timestamp = u32::try_from(datetime.timestamp()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} does not fit in a 32 bits timestamp", datetime),
+            )
+        })?;
+        Frame::new_from_slice(Self::APPLICATION_ID, &timestamp.to_be_bytes())
     }
 
-    pub(crate) fn request_now() -> std::io::Result<Frame> {
+    pub fn request_now() -> std::io::Result<Frame> {
         let datetime = Utc::now();
         Self::request(datetime)
     }
@@ -1,9 +1,11 @@
 use std::time::Duration;
 
 use async_trait::async_trait;
-use tokio::time::timeout;
 
-use super::{Application, Fallback, Frame};
+use super::{Application, Fallback, Frame, Response, FALLBACK_TIMEOUT};
+use crate::client::{
+    bytes, Answer, ApplicationSpec, ArgumentKind, ArgumentSpec, Decoded, OperationSpec,
+};
 
 const MAX_UDP_COMMAND_LENGTH: usize = 124;
 // const UDP_CUSTOM_CODE: [u8; 4] = [0, 0, 0xea, 0x62];
@@ -14,7 +16,7 @@ pub struct UdpBackup<F> {
 
 #[async_trait]
 impl<F: Fallback> Application for UdpBackup<F> {
-    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Option<Frame>> {
+    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
         if frame.data().len() > MAX_UDP_COMMAND_LENGTH {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
@@ -27,8 +29,8 @@ impl<F: Fallback> Application for UdpBackup<F> {
 
         let send_future = self.fallback.fallback(udp_commnad);
         // the custom udp command does not return a result.
-        let _reply = timeout(Duration::from_millis(100), send_future).await??;
-        Ok(None)
+        let _reply = send_future.await?;
+        Ok(Response::Empty)
     }
 
     fn application_id(&self) -> u8 {
@@ -38,12 +40,41 @@ impl<F: Fallback> Application for UdpBackup<F> {
     fn application_name(&self) -> &'static str{
         "UDP command over tcsp"
     }
+
+    fn default_timeout(&self) -> Option<Duration> {
+        Some(FALLBACK_TIMEOUT)
+    }
 }
 
 impl<F> UdpBackup<F> {
-    pub(crate) const APPLICATION_ID: u8 = 6;
+    pub const APPLICATION_ID: u8 = 6;
+    pub const SPEC: ApplicationSpec = ApplicationSpec {
+        name: "udp-backup",
+        id: Self::APPLICATION_ID,
+        about: "Send a UDP command, written in hex, through the node.",
+        operations: &[OperationSpec {
+            name: None,
+            about: "",
+            arguments: &[ArgumentSpec {
+                name: "command",
+                help: "The UDP command.",
+                kind: ArgumentKind::Hex,
+            }],
+            answer: Answer::None,
+            // the destination is set again by the client
+            request: |arguments| Self::generate_request(bytes(arguments, 0)?.to_vec(), 0),
+            parse: |arguments, _| {
+                let len = bytes(arguments, 0)?.len() as u64;
+                Ok(Decoded::Record(vec![
+                    ("status", Decoded::Text("sent".to_owned())),
+                    ("len", Decoded::Number(len)),
+                ]))
+            },
+        }],
+    };
 
-    pub(crate) fn generate_request(data: Vec<u8>, dest_id: u8) -> std::io::Result<Vec<Frame>> {
+    /// Split a UDP command into request frames of at most 124 bytes. They are not answered.
+    pub fn generate_request(data: Vec<u8>, dest_id: u8) -> std::io::Result<Vec<Frame>> {
         let mut frame_vec = Vec::new();
         for chunk in data.chunks(MAX_UDP_COMMAND_LENGTH) {
             let mut frame = Frame::new(Self::APPLICATION_ID);
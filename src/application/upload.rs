@@ -1,7 +1,34 @@
 use async_trait::async_trait;
 use tokio::sync::Mutex;
 
-use super::{Application, Fallback, Frame};
+use crate::protocol::{Decode, Encode, Remaining};
+
+use super::{Application, Fallback, Frame, Response};
+
+const UPLOAD_ACK: u8 = 0xAA;
+
+/// A chunk of the uploaded file, numbered from 0 to `sum`.
+#[derive(Decode)]
+struct DataChunk {
+    data_type: u8,
+    id: u16,
+    sum: u16,
+    _data: Remaining,
+}
+
+/// Acknowledges the start or the end of an upload.
+#[derive(Encode)]
+struct UploadAck {
+    data_type: u8,
+    status: u8,
+}
+
+#[derive(Encode)]
+struct ChunkAck {
+    data_type: u8,
+    id: u16,
+    status: u8,
+}
 
 pub struct UploadCommand<F> {
     fallback: F,
@@ -20,28 +47,30 @@ enum UploadState {
 
 #[async_trait]
 impl<F: Fallback> Application for UploadCommand<F> {
-    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Option<Frame>> {
+    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
         let mut guard = self.state.lock().await;
         let state = guard.as_mut();
         match state {
             UploadState::UploadStart => {
-                let data_type = frame.data()[0];
-                let response = Frame::new_from_slice(Self::APPLICATION_ID, &[data_type, 0xAA])?;
+                let data_type = u8::decode(&mut frame.data())?;
+                let ack = UploadAck {
+                    data_type,
+                    status: UPLOAD_ACK,
+                };
+                let response = Frame::new_from_slice(Self::APPLICATION_ID, &ack.to_bytes()?)?;
                 *state = UploadState::UploadResponse(data_type);
-                Ok(Some(response))
+                Ok(Response::Single(response))
             }
             UploadState::UploadResponse(data_type) => {
-                let data = frame.data();
-                if *data_type != data[0] {
+                let chunk = DataChunk::from_bytes(frame.data())?;
+                if *data_type != chunk.data_type {
                     return Err(std::io::Error::new(
                         std::io::ErrorKind::InvalidData,
                         "data type mismatch",
                     ));
                 }
 
-                let data_frame_id = u16::from_be_bytes([data[1], data[2]]);
-                let data_frame_sum = u16::from_be_bytes([data[3], data[4]]);
-                if data_frame_id != 0 {
+                if chunk.id != 0 {
                     return Err(std::io::Error::new(
                         std::io::ErrorKind::InvalidData,
                         "data frame id not match",
@@ -50,50 +79,55 @@ impl<F: Fallback> Application for UploadCommand<F> {
 
                 // TODO: Handle data with zeromq
 
-                let response = Frame::new_from_slice(
-                    Self::APPLICATION_ID,
-                    &[*data_type, data[1], data[2], 0xAA],
-                )?;
+                let ack = ChunkAck {
+                    data_type: *data_type,
+                    id: chunk.id,
+                    status: UPLOAD_ACK,
+                };
+                let response = Frame::new_from_slice(Self::APPLICATION_ID, &ack.to_bytes()?)?;
 
-                if data_frame_sum != data_frame_id {
+                if chunk.sum != chunk.id {
                     *state = UploadState::DataResponse(*data_type);
                 } else {
                     *state = UploadState::UploadDone(*data_type);
                 }
-                Ok(Some(response))
+                Ok(Response::Single(response))
             }
             UploadState::DataResponse(data_type) => {
-                let data = frame.data();
-                if *data_type != data[0] {
+                let chunk = DataChunk::from_bytes(frame.data())?;
+                if *data_type != chunk.data_type {
                     return Err(std::io::Error::new(
                         std::io::ErrorKind::InvalidData,
                         "data type mismatch",
                     ));
                 }
 
-                let data_frame_id = u16::from_be_bytes([data[1], data[2]]);
-                let data_frame_sum = u16::from_be_bytes([data[3], data[4]]);
-
                 // TODO: Handle data with zeromq
 
-                let response = Frame::new_from_slice(
-                    Self::APPLICATION_ID,
-                    &[*data_type, data[1], data[2], 0xAA],
-                )?;
+                let ack = ChunkAck {
+                    data_type: *data_type,
+                    id: chunk.id,
+                    status: UPLOAD_ACK,
+                };
+                let response = Frame::new_from_slice(Self::APPLICATION_ID, &ack.to_bytes()?)?;
 
-                if data_frame_sum != data_frame_id {
+                if chunk.sum != chunk.id {
                     *state = UploadState::DataResponse(*data_type);
                 } else {
                     *state = UploadState::UploadDone(*data_type);
                 }
-                Ok(Some(response))
+                Ok(Response::Single(response))
             }
             UploadState::UploadDone(data_type) => {
-                let response = Frame::new_from_slice(Self::APPLICATION_ID, &[*data_type, 0xAA])?;
+                let ack = UploadAck {
+                    data_type: *data_type,
+                    status: UPLOAD_ACK,
+                };
+                let response = Frame::new_from_slice(Self::APPLICATION_ID, &ack.to_bytes()?)?;
                 *state = UploadState::Done;
-                Ok(Some(response))
+                Ok(Response::Single(response))
             }
-            UploadState::Done => Ok(None),
+            UploadState::Done => Ok(Response::Empty),
         }
     }
 
@@ -0,0 +1,313 @@
+use std::{
+    collections::BTreeSet,
+    fmt, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use log::LevelFilter;
+use serde::Deserialize;
+use tcsp::{
+    AccessPolicy, ApplicationOptions, Diagnostics, EchoCommand, Reboot, ResetNetwork, TeleMetry,
+    TimeSync, UdpBackup,
+};
+
+const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 2000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to read {path}: {source}")]
+    Read { path: PathBuf, source: io::Error },
+    #[error("invalid configuration: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("invalid configuration: {0}")]
+    Invalid(String),
+}
+
+/// The configuration of `tcsp-server`, read from a TOML file like:
+///
+/// ```toml
+/// node_id = 0x2a
+/// log_level = "info"
+///
+/// [[adaptors]]
+/// type = "can"
+/// rx = "can0"
+/// tx = "can0"
+///
+/// [[applications]]
+/// name = "telemetry"
+/// fallback = "tcp://127.0.0.1:5555"
+/// timeout_ms = 100
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The id of this node on the bus.
+    pub node_id: u8,
+    #[serde(default = "default_log_level")]
+    log_level: String,
+    pub adaptors: Vec<AdaptorConfig>,
+    pub applications: Vec<ApplicationConfig>,
+    /// How long to wait for a fallback endpoint at startup.
+    #[serde(default = "default_connect_timeout_ms")]
+    connect_timeout_ms: u64,
+    #[serde(default)]
+    pub access_policy: AccessPolicy,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum AdaptorConfig {
+    /// A `TyCanProtocol` adaptor on the given CAN interfaces.
+    Can { rx: String, tx: String },
+    /// A `Uart` adaptor on a serial device.
+    Uart { device: PathBuf, baud_rate: u32 },
+    /// A `Udp` link bound to `bind`, exchanging frames with `peer`.
+    Udp { bind: String, peer: String },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApplicationConfig {
+    pub name: ApplicationKind,
+    /// The ZeroMQ endpoint the application forwards its requests to.
+    pub fallback: Option<String>,
+    timeout_ms: Option<u64>,
+    dedup_ms: Option<u64>,
+    #[serde(default)]
+    ordered: bool,
+}
+
+/// A built-in application, named as the `tcsp` client names it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "String")]
+pub enum ApplicationKind {
+    Telemetry,
+    Echo,
+    TimeSync,
+    Reboot,
+    UdpBackup,
+    ResetNetwork,
+    Diagnostics,
+}
+
+impl ApplicationKind {
+    const ALL: [Self; 7] = [
+        Self::Telemetry,
+        Self::Echo,
+        Self::TimeSync,
+        Self::Reboot,
+        Self::UdpBackup,
+        Self::ResetNetwork,
+        Self::Diagnostics,
+    ];
+
+    /// Whether the application forwards its requests to a fallback endpoint.
+    pub fn needs_fallback(self) -> bool {
+        matches!(self, Self::Telemetry | Self::TimeSync | Self::UdpBackup)
+    }
+
+    /// The name in the spec of the application.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Telemetry => TeleMetry::<()>::SPEC.name,
+            Self::Echo => EchoCommand::SPEC.name,
+            Self::TimeSync => TimeSync::<()>::SPEC.name,
+            Self::Reboot => Reboot::SPEC.name,
+            Self::UdpBackup => UdpBackup::<()>::SPEC.name,
+            Self::ResetNetwork => ResetNetwork::SPEC.name,
+            Self::Diagnostics => Diagnostics::SPEC.name,
+        }
+    }
+}
+
+impl TryFrom<String> for ApplicationKind {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+            .ok_or_else(|| format!("unknown application `{}`", name))
+    }
+}
+
+impl fmt::Display for ApplicationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl ApplicationConfig {
+    pub fn options(&self) -> ApplicationOptions {
+        let mut options = ApplicationOptions::new().ordered(self.ordered);
+        if let Some(timeout) = self.timeout_ms {
+            options = options.timeout(Duration::from_millis(timeout));
+        }
+        if let Some(window) = self.dedup_ms {
+            options = options.dedup(Duration::from_millis(window));
+        }
+        options
+    }
+}
+
+impl Config {
+    /// Read and validate the configuration file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        std::fs::read_to_string(path)
+            .map_err(|source| ConfigError::Read {
+                path: path.to_owned(),
+                source,
+            })?
+            .parse()
+    }
+
+    pub fn log_level(&self) -> LevelFilter {
+        // checked by `validate`
+        self.log_level.parse().unwrap_or(LevelFilter::Info)
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_millis(self.connect_timeout_ms)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: String| Err(ConfigError::Invalid(message));
+        if LevelFilter::from_str(&self.log_level).is_err() {
+            return invalid(format!(
+                "unknown log_level \"{}\", expect one of off, error, warn, info, debug, trace",
+                self.log_level
+            ));
+        }
+        if self.adaptors.is_empty() {
+            return invalid("at least one adaptor is required".to_owned());
+        }
+        for (index, adaptor) in self.adaptors.iter().enumerate() {
+            match adaptor {
+                AdaptorConfig::Can { rx, tx } if rx.is_empty() || tx.is_empty() => {
+                    return invalid(format!("adaptors[{}]: empty CAN interface name", index));
+                }
+                AdaptorConfig::Uart { baud_rate: 0, .. } => {
+                    return invalid(format!("adaptors[{}]: baud_rate must not be 0", index));
+                }
+                _ => {}
+            }
+        }
+        if self.applications.is_empty() {
+            return invalid("at least one application is required".to_owned());
+        }
+        let mut enabled = BTreeSet::new();
+        for application in self.applications.iter() {
+            let name = application.name;
+            if !enabled.insert(name) {
+                return invalid(format!("application {} is enabled twice", name));
+            }
+            match (name.needs_fallback(), &application.fallback) {
+                (true, None) => {
+                    return invalid(format!("application {} requires a fallback endpoint", name));
+                }
+                (false, Some(_)) => {
+                    return invalid(format!(
+                        "application {} does not use a fallback endpoint",
+                        name
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let config: Config = toml::from_str(s)?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+fn default_log_level() -> String {
+    "info".to_owned()
+}
+
+fn default_connect_timeout_ms() -> u64 {
+    DEFAULT_CONNECT_TIMEOUT_MS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApplicationKind, Config, ConfigError};
+
+    #[test]
+    fn test_example_config() {
+        let config: Config = include_str!("../../../examples/tcsp-server.toml")
+            .parse()
+            .unwrap();
+        assert_eq!(config.node_id, 0x2a);
+        assert_eq!(config.adaptors.len(), 1);
+        assert!(config
+            .applications
+            .iter()
+            .any(|application| application.name == ApplicationKind::Diagnostics));
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let check = |s: &str, expect: &str| {
+            let error = s.parse::<Config>().unwrap_err();
+            assert!(
+                error.to_string().contains(expect),
+                "\"{}\" does not mention \"{}\"",
+                error,
+                expect
+            );
+        };
+        let adaptor = "[[adaptors]]\ntype = \"can\"\nrx = \"can0\"\ntx = \"can0\"\n";
+        check(
+            &format!("node_id = 1\napplications = []\n{}", adaptor),
+            "at least one application",
+        );
+        check(
+            &format!(
+                "node_id = 1\nlog_level = \"loud\"\n{}[[applications]]\nname = \"echo\"\n",
+                adaptor
+            ),
+            "unknown log_level",
+        );
+        check(
+            &format!(
+                "node_id = 1\n{}[[applications]]\nname = \"telemetry\"\n",
+                adaptor
+            ),
+            "requires a fallback endpoint",
+        );
+        check(
+            &format!(
+                "node_id = 1\n{}[[applications]]\nname = \"echo\"\n[[applications]]\nname = \"echo\"\n",
+                adaptor
+            ),
+            "enabled twice",
+        );
+        check(
+            &format!(
+                "node_id = 1\n{}[[applications]]\nname = \"upload\"\n",
+                adaptor
+            ),
+            "unknown application `upload`",
+        );
+        check(
+            "node_id = 1\n[[adaptors]]\ntype = \"uart\"\ndevice = \"/dev/ttyS0\"\nbaud = 9600\n",
+            "unknown field `baud`",
+        );
+        assert!(matches!(
+            Config::load("/nonexistent/tcsp-server.toml"),
+            Err(ConfigError::Read { .. })
+        ));
+    }
+}
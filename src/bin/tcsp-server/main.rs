@@ -0,0 +1,165 @@
+//! A TCSP server configured by a TOML file, see `examples/tcsp-server.toml`.
+use std::{collections::HashMap, path::PathBuf, process::ExitCode, sync::Arc};
+
+use clap::Parser;
+use tcsp::{
+    DeviceAdaptor, Diagnostics, EchoCommand, Reboot, ResetNetwork, TcspServerBuilder, TeleMetry,
+    TimeSync, TyCanProtocol, Uart, Udp, UdpBackup, ZeromqSocket,
+};
+use tokio::time::timeout;
+
+mod config;
+
+use config::{AdaptorConfig, ApplicationKind, Config};
+
+#[derive(Parser, Debug)]
+#[command(about, long_about = None)]
+struct Args {
+    /// The configuration file.
+    #[arg(default_value = "/etc/tcsp/server.toml")]
+    config: PathBuf,
+    /// Validate the configuration file and exit.
+    #[arg(long)]
+    check: bool,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    let config = match Config::load(&args.config) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}: {}", args.config.display(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if args.check {
+        println!("{}: ok", args.config.display());
+        return ExitCode::SUCCESS;
+    }
+    env_logger::Builder::new()
+        .filter_level(config.log_level())
+        .init();
+    match run(config).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            log::error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(config: Config) -> Result<(), String> {
+    let fallbacks = connect_fallbacks(&config).await?;
+    let mut adaptors = Vec::new();
+    for adaptor in config.adaptors.iter() {
+        adaptors.push(open_adaptor(config.node_id, adaptor).await?);
+    }
+    let mut adaptors = adaptors.into_iter();
+    let Some(first) = adaptors.next() else {
+        return Err("no adaptor configured".to_owned());
+    };
+    let mut builder = adaptors.fold(
+        TcspServerBuilder::new(first),
+        TcspServerBuilder::with_adaptor,
+    );
+    for application in config.applications.iter() {
+        let fallback = application
+            .fallback
+            .as_ref()
+            .and_then(|endpoint| fallbacks.get(endpoint))
+            .cloned();
+        let options = application.options();
+        builder = match (application.name, fallback) {
+            (ApplicationKind::Telemetry, Some(socket)) => {
+                builder.with_application_options(Arc::new(TeleMetry::new(socket)), options)
+            }
+            (ApplicationKind::TimeSync, Some(socket)) => {
+                builder.with_application_options(Arc::new(TimeSync::new(socket)), options)
+            }
+            (ApplicationKind::UdpBackup, Some(socket)) => {
+                builder.with_application_options(Arc::new(UdpBackup::new(socket)), options)
+            }
+            (ApplicationKind::Echo, _) => {
+                builder.with_application_options(Arc::new(EchoCommand {}), options)
+            }
+            (ApplicationKind::Reboot, _) => {
+                builder.with_application_options(Arc::new(Reboot {}), options)
+            }
+            (ApplicationKind::ResetNetwork, _) => {
+                builder.with_application_options(Arc::new(ResetNetwork {}), options)
+            }
+            (ApplicationKind::Diagnostics, _) => {
+                builder.with_application_options(Arc::new(Diagnostics::new()), options)
+            }
+            (name, None) => return Err(format!("application {} has no fallback", name)),
+        };
+    }
+    let server = builder
+        .with_access_policy(config.access_policy)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let shutdown = server.shutdown_handle();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            log::info!("interrupted, stopping the server");
+            shutdown.shutdown();
+        }
+    });
+    server.listen().await.map_err(|e| e.to_string())
+}
+
+/// Connect one socket per distinct fallback endpoint, shared by the applications using it.
+async fn connect_fallbacks(config: &Config) -> Result<HashMap<String, ZeromqSocket>, String> {
+    let mut fallbacks = HashMap::new();
+    for endpoint in config
+        .applications
+        .iter()
+        .filter_map(|application| application.fallback.as_ref())
+    {
+        if fallbacks.contains_key(endpoint) {
+            continue;
+        }
+        let socket = ZeromqSocket::new();
+        match timeout(config.connect_timeout(), socket.connect(endpoint)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => return Err(format!("failed to connect {}: {}", endpoint, e)),
+            Err(_) => return Err(format!("timed out connecting {}", endpoint)),
+        }
+        log::info!("connected fallback {}", endpoint);
+        fallbacks.insert(endpoint.clone(), socket);
+    }
+    Ok(fallbacks)
+}
+
+async fn open_adaptor(
+    node_id: u8,
+    adaptor: &AdaptorConfig,
+) -> Result<Box<dyn DeviceAdaptor>, String> {
+    match adaptor {
+        AdaptorConfig::Can { rx, tx } => {
+            let can = TyCanProtocol::new(node_id, rx, tx)
+                .await
+                .map_err(|e| format!("failed to open CAN {}/{}: {}", rx, tx, e))?;
+            log::info!("listen on CAN rx={} tx={}", rx, tx);
+            Ok(Box::new(can))
+        }
+        AdaptorConfig::Uart { device, baud_rate } => {
+            if !device.exists() {
+                return Err(format!("serial device {} does not exist", device.display()));
+            }
+            let name = device.to_string_lossy();
+            let uart = Uart::new(&name, *baud_rate).await;
+            log::info!("listen on {} at {} baud", name, baud_rate);
+            Ok(Box::new(uart))
+        }
+        AdaptorConfig::Udp { bind, peer } => {
+            let udp = Udp::new(bind.as_str(), peer.as_str())
+                .await
+                .map_err(|e| format!("failed to open UDP {} to {}: {}", bind, peer, e))?;
+            log::info!("listen on UDP {} with peer {}", bind, peer);
+            Ok(Box::new(udp))
+        }
+    }
+}
@@ -0,0 +1,423 @@
+//! An interactive console reading the commands of `tcsp` line by line.
+//!
+//! The commands are the subcommands of `tcsp`, built from the registry of the built-in
+//! applications, so an application added to `REGISTRY` is available here too, with its
+//! completion. Frames which answer no request are printed above the prompt as they arrive. The commands of a session can be saved into a script and replayed with `run`.
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use clap::{error::ErrorKind, CommandFactory, FromArgMatches, Parser, Subcommand};
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    history::DefaultHistory, validate::Validator, Context, Editor, ExternalPrinter, Helper,
+};
+use serde_json::json;
+use tcsp::{DeviceAdaptor, Frame, TcspClient, REGISTRY};
+use tokio::sync::mpsc;
+
+use super::{
+    execute,
+    output::{self, application_name, parse_id, to_hex},
+    parse_command, print_error, request_commands, CliError,
+};
+
+const HISTORY_FILE: &str = ".tcsp_history";
+
+/// The commands of the tcsp console.
+#[derive(Parser, Debug)]
+#[command(name = "console", no_binary_name = true, disable_version_flag = true)]
+struct Line {
+    /// Send to this node instead of the current destination.
+    #[arg(short, long, value_parser = parse_id)]
+    dest: Option<u8>,
+    /// A command of the console, unless the line sends a request.
+    #[command(subcommand)]
+    command: Option<ConsoleCommand>,
+}
+
+/// The parser of a line: the commands of the console and those sending a request.
+fn line_command() -> clap::Command {
+    Line::command()
+        .subcommand_required(true)
+        .subcommands(request_commands())
+}
+
+#[derive(Subcommand, Debug)]
+enum ConsoleCommand {
+    /// Set the destination of the next commands.
+    Dest {
+        #[arg(value_parser = parse_id)]
+        node: u8,
+    },
+    /// Save the commands sent in this session into a script.
+    Save { path: PathBuf },
+    /// Run the commands of a script, stopping at the first failure.
+    Run { path: PathBuf },
+    /// Leave the console.
+    #[command(alias = "quit")]
+    Exit,
+}
+
+enum Flow {
+    Continue,
+    Exit,
+}
+
+pub struct Console<D> {
+    client: TcspClient<D>,
+    dest: Option<u8>,
+    /// The nodes addressed or heard from, to complete destinations.
+    nodes: Arc<Mutex<BTreeSet<u8>>>,
+    /// The commands sent in this session, each with its destination, for `save`.
+    script: Vec<String>,
+    json: bool,
+}
+
+impl<D: DeviceAdaptor + 'static> Console<D> {
+    /// `client` should send its unsolicited frames to the receiver given to `interact`.
+    pub fn new(client: TcspClient<D>, dest: Option<u8>, json: bool) -> Self {
+        Self {
+            client,
+            dest,
+            nodes: Arc::new(Mutex::new(dest.into_iter().collect())),
+            script: Vec::new(),
+            json,
+        }
+    }
+
+    /// Read and execute lines until `exit` or the end of the input.
+    pub async fn interact(
+        mut self,
+        mut unsolicited: mpsc::Receiver<Frame>,
+    ) -> Result<(), CliError> {
+        let mut editor = Editor::<Completion, DefaultHistory>::new()
+            .map_err(|e| CliError::Console(e.to_string()))?;
+        editor.set_helper(Some(Completion {
+            line: line_command(),
+            nodes: Arc::clone(&self.nodes),
+        }));
+        let history = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+        if let Some(path) = &history {
+            // there is no history before the first session
+            let _ = editor.load_history(path);
+        }
+
+        let mut printer: Box<dyn ExternalPrinter + Send> = match editor.create_external_printer() {
+            Ok(printer) => Box::new(printer),
+            Err(_) => Box::new(Stdout),
+        };
+        let nodes = Arc::clone(&self.nodes);
+        let json = self.json;
+        let pane = tokio::spawn(async move {
+            while let Some(frame) = unsolicited.recv().await {
+                nodes
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(frame.meta().src_id());
+                let _ = printer.print(unsolicited_line(&frame, json));
+            }
+        });
+
+        let result = loop {
+            let prompt = match self.dest {
+                Some(dest) => format!("tcsp@{}> ", dest),
+                None => "tcsp> ".to_owned(),
+            };
+            let line = match tokio::task::block_in_place(|| editor.readline(&prompt)) {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break Ok(()),
+                Err(e) => break Err(CliError::Console(e.to_string())),
+            };
+            let _ = editor.add_history_entry(line.as_str());
+            match self.execute_line(&line).await {
+                Ok(Flow::Continue) => {}
+                Ok(Flow::Exit) => break Ok(()),
+                Err(e) => print_error(&e, self.json),
+            }
+        };
+        pane.abort();
+        if let Some(path) = &history {
+            if let Err(e) = editor.save_history(path) {
+                log::warn!("failed to save the history to {}: {}", path.display(), e);
+            }
+        }
+        result
+    }
+
+    async fn execute_line(&mut self, line: &str) -> Result<Flow, CliError> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(Flow::Continue);
+        }
+        let words = split_line(line).map_err(CliError::Argument)?;
+        let argument = |e: clap::Error| {
+            let message = e.to_string();
+            let message = message.trim_start_matches("error: ").trim_end();
+            CliError::Argument(message.to_owned())
+        };
+        let matches = match line_command().try_get_matches_from(words) {
+            Ok(matches) => matches,
+            Err(e) if e.kind() == ErrorKind::DisplayHelp => {
+                print!("{}", e);
+                return Ok(Flow::Continue);
+            }
+            Err(e) => return Err(argument(e)),
+        };
+        let parsed = Line::from_arg_matches(&matches).map_err(argument)?;
+        let request = match matches.subcommand() {
+            Some((name, matches)) => parse_command(name, matches).map_err(argument)?,
+            None => None,
+        };
+        if let Some(command) = request {
+            let dest = parsed.dest.or(self.dest).ok_or_else(|| {
+                CliError::Argument("no destination, set one with `dest <node>`".to_owned())
+            })?;
+            self.remember(dest);
+            self.script.push(match parsed.dest {
+                Some(_) => line.to_owned(),
+                None => format!("-d {} {}", dest, line),
+            });
+            let value = execute(&self.client, dest, &command).await?;
+            output::print(&value, self.json);
+            return Ok(Flow::Continue);
+        }
+        let Some(command) = parsed.command else {
+            return Err(CliError::Argument("no command".to_owned()));
+        };
+        match command {
+            ConsoleCommand::Dest { node } => {
+                self.dest = Some(node);
+                self.remember(node);
+            }
+            ConsoleCommand::Save { path } => {
+                let script: String = self
+                    .script
+                    .iter()
+                    .map(|line| format!("{}\n", line))
+                    .collect();
+                fs::write(&path, script).map_err(|e| {
+                    CliError::Console(format!("failed to write {}: {}", path.display(), e))
+                })?;
+                println!("saved {} commands to {}", self.script.len(), path.display());
+            }
+            ConsoleCommand::Run { path } => {
+                let script = fs::read_to_string(&path).map_err(|e| {
+                    CliError::Console(format!("failed to read {}: {}", path.display(), e))
+                })?;
+                for (number, script_line) in script.lines().enumerate() {
+                    match Box::pin(self.execute_line(script_line)).await {
+                        Ok(Flow::Continue) => {}
+                        Ok(Flow::Exit) => return Ok(Flow::Exit),
+                        Err(e) => {
+                            return Err(CliError::Console(format!(
+                                "{}:{}: {}: {}",
+                                path.display(),
+                                number + 1,
+                                e.kind(),
+                                e
+                            )))
+                        }
+                    }
+                }
+            }
+            ConsoleCommand::Exit => return Ok(Flow::Exit),
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn remember(&self, node: u8) {
+        self.nodes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(node);
+    }
+}
+
+fn unsolicited_line(frame: &Frame, json: bool) -> String {
+    let src = frame.meta().src_id();
+    let application = application_name(frame.application());
+    if json {
+        let data = to_hex(frame.data());
+        json!({ "unsolicited": { "src": src, "application": application, "data": data } })
+            .to_string()
+    } else {
+        format!("<< node {} {}: {}", src, application, to_hex(frame.data()))
+    }
+}
+
+/// Split a line into words at whitespace, keeping quoted words together.
+fn split_line(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => word.get_or_insert_with(String::new).push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            None if c.is_whitespace() => words.extend(word.take()),
+            None => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("unclosed quote".to_owned());
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// The completions of the word ending `before`, and where that word starts.
+///
+/// Commands are completed from the subcommands of `line`, destinations from `nodes` and the
+/// application of `raw` from the built-in applications.
+fn candidates(line: &clap::Command, nodes: &BTreeSet<u8>, before: &str) -> (usize, Vec<String>) {
+    let start = before
+        .rfind(char::is_whitespace)
+        .map_or(0, |index| index + 1);
+    let mut current = line;
+    let mut arguments = 0;
+    let mut last = None;
+    for word in before[..start].split_whitespace() {
+        if !matches!(last, Some("-d" | "--dest")) && !word.starts_with('-') {
+            match current.find_subcommand(word) {
+                Some(subcommand) if arguments == 0 => current = subcommand,
+                _ => arguments += 1,
+            }
+        }
+        last = Some(word);
+    }
+    let options: Vec<String> = match (last, current.get_name(), arguments) {
+        (Some("-d" | "--dest"), _, _) | (_, "dest", 0) => nodes.iter().map(u8::to_string).collect(),
+        (_, "raw", 0) => REGISTRY
+            .iter()
+            .map(|application| application.name.to_owned())
+            .collect(),
+        (_, _, 0) => current
+            .get_subcommands()
+            .map(|subcommand| subcommand.get_name().to_owned())
+            .collect(),
+        _ => Vec::new(),
+    };
+    let prefix = &before[start..];
+    (
+        start,
+        options
+            .into_iter()
+            .filter(|option| option.starts_with(prefix))
+            .collect(),
+    )
+}
+
+struct Completion {
+    line: clap::Command,
+    nodes: Arc<Mutex<BTreeSet<u8>>>,
+}
+
+impl Completer for Completion {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let nodes = self.nodes.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(candidates(&self.line, &nodes, &line[..pos]))
+    }
+}
+
+impl Hinter for Completion {
+    type Hint = String;
+}
+
+impl Highlighter for Completion {}
+
+impl Validator for Completion {}
+
+impl Helper for Completion {}
+
+/// Prints the unsolicited frames when the input is not a terminal.
+struct Stdout;
+
+impl ExternalPrinter for Stdout {
+    fn print(&mut self, msg: String) -> rustyline::Result<()> {
+        println!("{}", msg);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{candidates, line_command, split_line, Console};
+    use crate::tests::echo_server;
+
+    #[test]
+    fn test_split_line() {
+        assert_eq!(split_line(" echo  hi ").unwrap(), ["echo", "hi"]);
+        assert_eq!(
+            split_line(r#"echo "hello world" ''"#).unwrap(),
+            ["echo", "hello world", ""]
+        );
+        assert!(split_line("echo 'hi").is_err());
+    }
+
+    #[test]
+    fn test_completion() {
+        let line = line_command();
+        let nodes = BTreeSet::from([1, 42]);
+        let complete = |before: &str| candidates(&line, &nodes, before);
+        let (start, options) = complete("te");
+        assert_eq!(start, 0);
+        assert_eq!(options, ["telemetry"]);
+        assert!(complete("").1.contains(&"time-sync".to_owned()));
+        assert_eq!(complete("reset-network ").1, ["list", "reset"]);
+        assert_eq!(complete("dest 4"), (5, vec!["42".to_owned()]));
+        assert_eq!(complete("-d 1 ech"), (5, vec!["echo".to_owned()]));
+        assert_eq!(complete("raw reb").1, ["reboot"]);
+        assert!(complete("echo hi ").1.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_script() {
+        let client = echo_server().await;
+        let mut console = Console::new(client, None, false);
+        let path = std::env::temp_dir().join(format!("tcsp-script-{}", std::process::id()));
+
+        assert!(console.execute_line("echo hi").await.is_err());
+        console.execute_line("dest 1").await.unwrap();
+        console.execute_line("echo 'hello world'").await.unwrap();
+        console.execute_line("-d 2 raw echo 01").await.unwrap();
+        console.execute_line("# a comment").await.unwrap();
+        console
+            .execute_line(&format!("save {}", path.display()))
+            .await
+            .unwrap();
+        let script = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(script, "-d 1 echo 'hello world'\n-d 2 raw echo 01\n");
+
+        console
+            .execute_line(&format!("run {}", path.display()))
+            .await
+            .unwrap();
+        assert_eq!(console.script.len(), 4);
+        std::fs::write(&path, "echo hi\nraw 100\n").unwrap();
+        let error = console
+            .execute_line(&format!("run {}", path.display()))
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains(":2: rejected"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,494 @@
+//! A ground-station client sending one command to a TCSP node and printing the decoded response,
+//! or an interactive console when no command is given.
+use std::{ffi::OsString, path::PathBuf, process::ExitCode, time::Duration};
+
+use chrono::{DateTime, Utc};
+use clap::{
+    error::ErrorKind, value_parser, Arg, ArgAction, ArgMatches, Args as _, CommandFactory,
+    FromArgMatches, Parser,
+};
+use serde_json::{json, Value};
+use tcsp::{
+    find_application, ArgumentKind, ArgumentSpec, ArgumentValue, ClientError, DeviceAdaptor, Frame,
+    OperationSpec, RetryPolicy, TcspClient, TcspClientBuilder, TyCanProtocol, Uart, Udp, REGISTRY,
+};
+use tokio::sync::mpsc;
+
+mod console;
+mod output;
+
+use console::Console;
+use output::{parse_application, parse_hex, parse_id};
+
+const UNSOLICITED_CAPACITY: usize = 64;
+
+#[derive(Parser, Debug)]
+#[command(
+    about,
+    long_about = None,
+    after_help = "Without a command, open the console.\n\nExit status: 0 on success, 1 on errors, 2 on invalid arguments, \
+                  3 when the node does not answer in time, 4 when the node rejects the request."
+)]
+struct Args {
+    #[command(flatten)]
+    link: Link,
+    /// The bit rate of the serial device.
+    #[arg(long, default_value_t = 115200)]
+    baud: u32,
+    /// The local address of the UDP link.
+    #[arg(long, default_value = "0.0.0.0:0")]
+    bind: String,
+    /// The node id of this client.
+    #[arg(long, default_value = "0", value_parser = parse_id)]
+    node: u8,
+    /// The node to send the command to, or the first destination of the console.
+    #[arg(short, long, value_parser = parse_id)]
+    dest: Option<u8>,
+    /// How long to wait for a response, in milliseconds.
+    #[arg(long, default_value_t = 5000)]
+    timeout: u64,
+    /// How many times to send a request which is not answered in time.
+    #[arg(long, default_value_t = 1)]
+    attempts: u32,
+    /// Print the responses and the errors as JSON.
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(clap::Args, Debug)]
+#[group(required = true, multiple = false)]
+struct Link {
+    /// The CAN interfaces to receive and send on, like `can0,can1`, or one for both.
+    #[arg(long, value_name = "RX[,TX]")]
+    can: Option<String>,
+    /// The serial device.
+    #[arg(long, value_name = "DEVICE")]
+    uart: Option<PathBuf>,
+    /// The address of the node on a UDP link, see the `udp` adaptor of `tcsp-server`.
+    #[arg(long, value_name = "ADDR")]
+    udp: Option<String>,
+}
+
+/// A request to send, parsed from one of the subcommands of `request_commands`.
+#[derive(Debug)]
+enum Command {
+    /// An operation of a built-in application, with the values of its arguments.
+    Operation {
+        operation: &'static OperationSpec,
+        arguments: Vec<ArgumentValue>,
+    },
+    Raw {
+        application: u8,
+        payload: Vec<u8>,
+        no_response: bool,
+    },
+}
+
+/// Send a payload, written in hex, to any application, by id or by name.
+#[derive(clap::Args, Debug)]
+struct Raw {
+    #[arg(value_parser = parse_application)]
+    application: u8,
+    #[arg(default_value = "")]
+    payload: String,
+    /// Do not wait for a response.
+    #[arg(long)]
+    no_response: bool,
+}
+
+#[derive(thiserror::Error, Debug)]
+enum CliError {
+    #[error("{0}")]
+    Link(String),
+
+    #[error("{0}")]
+    Argument(String),
+
+    #[error(transparent)]
+    Client(#[from] ClientError),
+
+    #[error("{0}")]
+    Console(String),
+}
+
+impl CliError {
+    fn kind(&self) -> &'static str {
+        match self {
+            CliError::Link(_) => "link",
+            CliError::Argument(_) => "argument",
+            CliError::Console(_) => "console",
+            CliError::Client(ClientError::Timeout(_) | ClientError::Unanswered { .. }) => "timeout",
+            CliError::Client(ClientError::Rejected(_) | ClientError::Response(_)) => "rejected",
+            CliError::Client(_) => "client",
+        }
+    }
+
+    fn exit_code(&self) -> ExitCode {
+        match self.kind() {
+            "argument" => ExitCode::from(2),
+            "timeout" => ExitCode::from(3),
+            "rejected" => ExitCode::from(4),
+            _ => ExitCode::FAILURE,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    env_logger::init();
+    let (args, command) = parse_args(std::env::args_os()).unwrap_or_else(|e| e.exit());
+    match run(&args, command).await {
+        Ok(value) => {
+            if !value.is_null() {
+                output::print(&value, args.json);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            print_error(&e, args.json);
+            e.exit_code()
+        }
+    }
+}
+
+fn print_error(e: &CliError, as_json: bool) {
+    if as_json {
+        println!("{}", json!({ "error": e.kind(), "message": e.to_string() }));
+    } else {
+        eprintln!("{}: {}", e.kind(), e);
+    }
+}
+
+/// Parse the command line. Without a command, the console is opened.
+fn parse_args<I, T>(argv: I) -> Result<(Args, Option<Command>), clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let mut line = Args::command().subcommands(request_commands());
+    let matches = line.try_get_matches_from_mut(argv)?;
+    let args = Args::from_arg_matches(&matches)?;
+    let command = match matches.subcommand() {
+        Some((name, matches)) => parse_command(name, matches).map_err(|e| e.format(&mut line))?,
+        None => None,
+    };
+    Ok((args, command))
+}
+
+/// The subcommands sending a request: the operations of the built-in applications, and `raw`.
+fn request_commands() -> Vec<clap::Command> {
+    let applications = REGISTRY
+        .iter()
+        .filter(|application| !application.operations.is_empty())
+        .map(|application| match application.operations {
+            [operation @ OperationSpec { name: None, .. }] => {
+                operation_command(application.name, application.about, operation)
+            }
+            operations => clap::Command::new(application.name)
+                .about(application.about)
+                .subcommand_required(true)
+                .subcommands(operations.iter().filter_map(|operation| {
+                    let name = operation.name?;
+                    Some(operation_command(name, operation.about, operation))
+                })),
+        });
+    let raw = Raw::augment_args(clap::Command::new("raw"))
+        .about("Send a payload, written in hex, to any application, by id or by name.");
+    applications.chain([raw]).collect()
+}
+
+fn operation_command(
+    name: &'static str,
+    about: &'static str,
+    operation: &OperationSpec,
+) -> clap::Command {
+    let command = clap::Command::new(name).about(about);
+    operation
+        .arguments
+        .iter()
+        .fold(command, |command, argument| {
+            let value_name = match argument.kind {
+                ArgumentKind::Content => "CONTENT",
+                ArgumentKind::Hex => "HEX",
+                ArgumentKind::Time => "TIME",
+            };
+            let arg = Arg::new(argument.name)
+                .value_name(value_name)
+                .help(argument.help);
+            match argument.kind {
+                ArgumentKind::Content => command.arg(arg.required(true)).arg(
+                    Arg::new("hex")
+                        .long("hex")
+                        .action(ArgAction::SetTrue)
+                        .help("The content is written in hex."),
+                ),
+                ArgumentKind::Hex => command.arg(arg.required(true).value_parser(parse_hex)),
+                ArgumentKind::Time => command.arg(
+                    arg.long(argument.name)
+                        .value_parser(value_parser!(DateTime<Utc>)),
+                ),
+            }
+        })
+}
+
+/// The request of the subcommand `name` of `request_commands`, `None` for another subcommand.
+fn parse_command(name: &str, matches: &ArgMatches) -> Result<Option<Command>, clap::Error> {
+    if name == "raw" {
+        let raw = Raw::from_arg_matches(matches)?;
+        return Ok(Some(Command::Raw {
+            application: raw.application,
+            payload: parse_hex(&raw.payload).map_err(invalid)?,
+            no_response: raw.no_response,
+        }));
+    }
+    let Some(application) = find_application(name) else {
+        return Ok(None);
+    };
+    let (operation, matches) = match application.operations {
+        [operation @ OperationSpec { name: None, .. }] => (operation, matches),
+        operations => {
+            let operation = matches.subcommand().and_then(|(name, matches)| {
+                let operation = operations
+                    .iter()
+                    .find(|operation| operation.name == Some(name))?;
+                Some((operation, matches))
+            });
+            operation.ok_or_else(|| {
+                clap::Error::raw(ErrorKind::MissingSubcommand, "missing operation")
+            })?
+        }
+    };
+    let arguments = operation
+        .arguments
+        .iter()
+        .map(|argument| argument_value(argument, matches))
+        .collect::<Result<_, _>>()?;
+    Ok(Some(Command::Operation {
+        operation,
+        arguments,
+    }))
+}
+
+fn argument_value(
+    argument: &ArgumentSpec,
+    matches: &ArgMatches,
+) -> Result<ArgumentValue, clap::Error> {
+    let value = match argument.kind {
+        ArgumentKind::Content => {
+            let content = matches
+                .get_one::<String>(argument.name)
+                .map_or("", String::as_str);
+            match matches.get_flag("hex") {
+                true => ArgumentValue::Bytes(parse_hex(content).map_err(invalid)?),
+                false => ArgumentValue::Bytes(content.as_bytes().to_vec()),
+            }
+        }
+        ArgumentKind::Hex => ArgumentValue::Bytes(
+            matches
+                .get_one::<Vec<u8>>(argument.name)
+                .cloned()
+                .unwrap_or_default(),
+        ),
+        ArgumentKind::Time => ArgumentValue::Time(
+            matches
+                .get_one::<DateTime<Utc>>(argument.name)
+                .copied()
+                .unwrap_or_else(Utc::now),
+        ),
+    };
+    Ok(value)
+}
+
+fn invalid(message: String) -> clap::Error {
+    clap::Error::raw(ErrorKind::InvalidValue, message)
+}
+
+async fn run(args: &Args, command: Option<Command>) -> Result<Value, CliError> {
+    let adaptor = open_link(args).await?;
+    let builder = TcspClientBuilder::new(adaptor)
+        .with_node_id(args.node)
+        .with_timeout(Duration::from_millis(args.timeout))
+        .with_retry(RetryPolicy::new(args.attempts));
+    match command {
+        Some(command) => {
+            let dest = args.dest.ok_or_else(|| {
+                CliError::Argument("--dest is required to send a command".to_owned())
+            })?;
+            execute(&builder.build(), dest, &command).await
+        }
+        None => {
+            let (unsolicited_tx, unsolicited_rx) = mpsc::channel(UNSOLICITED_CAPACITY);
+            let client = builder.with_unsolicited(unsolicited_tx).build();
+            Console::new(client, args.dest, args.json)
+                .interact(unsolicited_rx)
+                .await?;
+            Ok(Value::Null)
+        }
+    }
+}
+
+async fn open_link(args: &Args) -> Result<Box<dyn DeviceAdaptor>, CliError> {
+    let link = &args.link;
+    if let Some(can) = &link.can {
+        let (rx, tx) = can.split_once(',').unwrap_or((can, can));
+        let can = TyCanProtocol::new(args.node, rx, tx)
+            .await
+            .map_err(|e| CliError::Link(format!("failed to open CAN {}/{}: {}", rx, tx, e)))?;
+        return Ok(Box::new(can));
+    }
+    if let Some(device) = &link.uart {
+        if !device.exists() {
+            return Err(CliError::Link(format!(
+                "serial device {} does not exist",
+                device.display()
+            )));
+        }
+        return Ok(Box::new(
+            Uart::new(&device.to_string_lossy(), args.baud).await,
+        ));
+    }
+    if let Some(peer) = &link.udp {
+        let udp = Udp::new(args.bind.as_str(), peer.as_str())
+            .await
+            .map_err(|e| CliError::Link(format!("failed to open UDP to {}: {}", peer, e)))?;
+        return Ok(Box::new(udp));
+    }
+    Err(CliError::Argument("no link given".to_owned()))
+}
+
+async fn execute<D: DeviceAdaptor + 'static>(
+    client: &TcspClient<D>,
+    dest: u8,
+    command: &Command,
+) -> Result<Value, CliError> {
+    let value = match command {
+        Command::Operation {
+            operation,
+            arguments,
+        } => output::decoded(&client.invoke(dest, operation, arguments).await?),
+        Command::Raw {
+            application,
+            payload,
+            no_response,
+        } => {
+            let request =
+                Frame::new_from_slice(*application, payload).map_err(ClientError::from)?;
+            if *no_response {
+                client.send(dest, request).await?;
+                json!({ "status": "sent" })
+            } else {
+                let response = client.request(dest, request).await?;
+                let mut value = output::data(response.data());
+                value["application"] = response.application().into();
+                value
+            }
+        }
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{process::ExitCode, sync::Arc, time::Duration};
+
+    use tcsp::{
+        ClientError, EchoCommand, ErrorResponse, ErrorStatus, TcspClient, TcspClientBuilder,
+        TcspServerBuilder, Udp,
+    };
+
+    use super::{execute, parse_args, CliError};
+
+    /// A client linked over UDP to a server running the echo application.
+    pub(crate) async fn echo_server() -> TcspClient<Udp> {
+        let reserved = Udp::new("127.0.0.1:0", "127.0.0.1:9").await.unwrap();
+        let server_addr = reserved.local_addr().unwrap();
+        let client_link = Udp::new("127.0.0.1:0", server_addr).await.unwrap();
+        // the server only accepts datagrams from its peer, so rebind it connected to the client
+        drop(reserved);
+        let server_link = Udp::new(server_addr, client_link.local_addr().unwrap())
+            .await
+            .unwrap();
+        let server = TcspServerBuilder::new(server_link)
+            .with_application(Arc::new(EchoCommand {}))
+            .build()
+            .unwrap();
+        tokio::spawn(async move {
+            server.listen().await.unwrap();
+        });
+        TcspClientBuilder::new(client_link)
+            .with_timeout(Duration::from_millis(500))
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_commands_over_udp() {
+        let client = echo_server().await;
+
+        let parse = |line: &str| {
+            let (args, command) = parse_args(line.split(' ')).unwrap();
+            (args.dest.unwrap(), command.unwrap())
+        };
+        let (dest, command) = parse("tcsp --udp 127.0.0.1:1 -d 1 echo hello");
+        let value = execute(&client, dest, &command).await.unwrap();
+        assert_eq!(value["text"], "hello");
+
+        let (dest, command) = parse("tcsp --udp 127.0.0.1:1 -d 1 echo --hex 0aff");
+        let value = execute(&client, dest, &command).await.unwrap();
+        assert_eq!(value["data"], "0aff");
+
+        let (dest, command) = parse("tcsp --udp 127.0.0.1:1 -d 1 raw 0x64 0102");
+        let error = execute(&client, dest, &command).await.unwrap_err();
+        assert_eq!(error.kind(), "rejected");
+
+        // nobody listens on a released port
+        let reserved = Udp::new("127.0.0.1:0", "127.0.0.1:9").await.unwrap();
+        let nowhere = reserved.local_addr().unwrap();
+        drop(reserved);
+        let client = TcspClientBuilder::new(Udp::new("127.0.0.1:0", nowhere).await.unwrap())
+            .with_timeout(Duration::from_millis(50))
+            .build();
+        let error = execute(&client, dest, &command).await.unwrap_err();
+        assert!(matches!(error, CliError::Client(_)));
+        assert_eq!(error.kind(), "timeout");
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let code = |error: CliError| error.exit_code();
+        assert_eq!(
+            code(CliError::Argument("odd hex".to_owned())),
+            ExitCode::from(2)
+        );
+        assert_eq!(
+            code(ClientError::Timeout(Duration::from_secs(1)).into()),
+            ExitCode::from(3)
+        );
+        let rejected = ErrorResponse::new(ErrorStatus::UnknownApplication, 100, "unknown");
+        assert_eq!(
+            code(ClientError::Rejected(rejected).into()),
+            ExitCode::from(4)
+        );
+        assert_eq!(
+            code(CliError::Link("no such device".to_owned())),
+            ExitCode::FAILURE
+        );
+    }
+
+    #[test]
+    fn test_links_are_exclusive() {
+        assert!(parse_args(["tcsp", "-d", "1", "reboot"]).is_err());
+        assert!(parse_args(["tcsp", "--udp", "127.0.0.1:1"]).is_ok());
+        assert!(parse_args([
+            "tcsp",
+            "--udp",
+            "127.0.0.1:1",
+            "--can",
+            "can0",
+            "-d",
+            "1",
+            "reboot"
+        ])
+        .is_err());
+        assert!(parse_args(["tcsp", "--can", "can0,can1", "-d", "0x2a", "reboot"]).is_ok());
+    }
+}
@@ -0,0 +1,164 @@
+//! Parsing of the command line values and printing of the decoded responses.
+use std::fmt::Write;
+
+use serde_json::{Map, Value};
+use tcsp::{find_application, Decoded};
+
+/// Parse bytes written in hex, optionally separated by spaces, `:` or `-`, like `0a0b` or `0a:0b`.
+pub fn parse_hex(s: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<u8> = s
+        .bytes()
+        .filter(|c| !matches!(c, b' ' | b':' | b'-'))
+        .collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(format!("odd number of hex digits in {:?}", s));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| format!("invalid hex digits in {:?}", s))
+        })
+        .collect()
+}
+
+/// Parse a node or an application id, in decimal or with a `0x` prefix.
+pub fn parse_id(s: &str) -> Result<u8, String> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|e| format!("invalid id {:?}: {}", s, e))
+}
+
+/// Parse an application id, or the name of a built-in application.
+pub fn parse_application(s: &str) -> Result<u8, String> {
+    find_application(s).map_or_else(|| parse_id(s), |application| Ok(application.id))
+}
+
+/// The name of a built-in application, or its id.
+pub fn application_name(id: u8) -> String {
+    tcsp::application_name(id).map_or_else(|| id.to_string(), str::to_owned)
+}
+
+pub fn to_hex(data: &[u8]) -> String {
+    data.iter().fold(String::new(), |mut s, byte| {
+        let _ = write!(s, "{:02x}", byte);
+        s
+    })
+}
+
+/// Response data, with its text if it is printable.
+pub fn data(data: &[u8]) -> Value {
+    let mut object = Map::new();
+    object.insert("data".to_owned(), Value::String(to_hex(data)));
+    if let Ok(text) = std::str::from_utf8(data) {
+        if !text.chars().any(char::is_control) {
+            object.insert("text".to_owned(), Value::String(text.to_owned()));
+        }
+    }
+    Value::Object(object)
+}
+
+/// A decoded response, with the bytes shown as `data`.
+pub fn decoded(decoded: &Decoded) -> Value {
+    match decoded {
+        Decoded::Bytes(bytes) => data(bytes),
+        Decoded::Text(text) => Value::String(text.clone()),
+        Decoded::Number(number) => Value::from(*number),
+        Decoded::List(items) => Value::Array(items.iter().map(self::decoded).collect()),
+        Decoded::Record(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(name, field)| ((*name).to_owned(), self::decoded(field)))
+                .collect(),
+        ),
+    }
+}
+
+/// Print `value` as one line of JSON, or as `key: value` lines for humans.
+pub fn print(value: &Value, as_json: bool) {
+    if as_json {
+        println!("{}", value);
+    } else {
+        print!("{}", human(value, 0));
+    }
+}
+
+fn human(value: &Value, indent: usize) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Object(object) => {
+            for (key, field) in object {
+                let _ = match field {
+                    Value::Object(_) => {
+                        write!(out, "{:indent$}{}:\n{}", "", key, human(field, indent + 2))
+                    }
+                    _ => writeln!(out, "{:indent$}{}: {}", "", key, scalar(field)),
+                };
+            }
+        }
+        _ => {
+            let _ = writeln!(out, "{:indent$}{}", "", scalar(value));
+        }
+    }
+    out
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(scalar).collect::<Vec<_>>().join(" "),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use tcsp::Decoded;
+
+    use super::{application_name, data, decoded, human, parse_application, parse_hex, parse_id};
+
+    #[test]
+    fn test_parse_values() {
+        assert_eq!(parse_hex("0a0B ff").unwrap(), [0x0a, 0x0b, 0xff]);
+        assert_eq!(parse_hex("01:02-03").unwrap(), [1, 2, 3]);
+        assert!(parse_hex("abc").is_err());
+        assert!(parse_hex("zz").is_err());
+        assert_eq!(parse_id("42").unwrap(), 42);
+        assert_eq!(parse_id("0x2a").unwrap(), 42);
+        assert!(parse_id("256").is_err());
+        assert_eq!(parse_application("echo").unwrap(), 2);
+        assert_eq!(parse_application("100").unwrap(), 100);
+        assert!(parse_application("ping").is_err());
+        assert_eq!(application_name(0), "telemetry");
+        assert_eq!(application_name(100), "100");
+    }
+
+    #[test]
+    fn test_human_output() {
+        assert_eq!(data(b"hi"), json!({ "data": "6869", "text": "hi" }));
+        assert_eq!(data(&[0, 1]), json!({ "data": "0001" }));
+        let value = decoded(&Decoded::Record(vec![(
+            "eth0",
+            Decoded::Record(vec![
+                ("ip", Decoded::Text("10.0.0.1".to_owned())),
+                (
+                    "flags",
+                    Decoded::List(vec![
+                        Decoded::Text("UP".to_owned()),
+                        Decoded::Text("RUNNING".to_owned()),
+                    ]),
+                ),
+            ]),
+        )]));
+        assert_eq!(
+            human(&value, 0),
+            "eth0:\n  flags: UP RUNNING\n  ip: 10.0.0.1\n"
+        );
+    }
+}
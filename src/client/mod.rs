@@ -0,0 +1,482 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    io,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+
+use crate::{
+    adaptor::{DeviceAdaptor, DeviceAdaptorError, Frame as BusFrame},
+    protocol::{v1::error_response::ERROR_RESPONSE_APPLICATION_ID, ErrorResponse, Frame},
+    server::Backoff,
+};
+
+mod registry;
+mod retry;
+mod stubs;
+
+pub use registry::{
+    application_name, find_application, Answer, ApplicationSpec, ArgumentKind, ArgumentSpec,
+    ArgumentValue, Decoded, OperationSpec, REGISTRY,
+};
+pub(crate) use registry::{bytes, single, status, time};
+pub use retry::RetryPolicy;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+/// The node id of the on-board computer, which the nodes answer as requester.
+const DEFAULT_NODE_ID: u8 = 0;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("No response within {0:?}")]
+    Timeout(Duration),
+
+    /// None of the attempts of a request with retries was answered.
+    #[error("No response after {attempts} attempts in {elapsed:?}")]
+    Unanswered { attempts: u32, elapsed: Duration },
+
+    #[error("Request rejected:{0}")]
+    Rejected(ErrorResponse),
+
+    #[error("Failed to send the request:{0}")]
+    Adaptor(DeviceAdaptorError),
+
+    #[error("Invalid frame:{0}")]
+    Frame(#[from] io::Error),
+
+    /// The response could not be parsed, or reports that the application failed.
+    #[error("Unexpected response:{0}")]
+    Response(io::Error),
+
+    #[error("All request ids to node {dest} and application {application} are in use")]
+    Busy { dest: u8, application: u8 },
+}
+
+/// A response, and how many times its request was sent.
+#[derive(Debug)]
+pub struct Reply {
+    pub frame: Frame,
+    pub attempts: u32,
+}
+
+/// Matches a response to its request: the node answering, the application and the request id.
+/// The node is left out on an adaptor without addressing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct PendingKey {
+    node: u8,
+    application: u8,
+    id: u8,
+}
+
+/// Where the responses to a pending request go.
+enum Waiter {
+    /// The first response completes the request.
+    Single(oneshot::Sender<Frame>),
+    /// Every response is kept until the request is unregistered.
+    Frames(mpsc::UnboundedSender<Frame>),
+}
+
+struct ClientInner<D> {
+    adaptor: D,
+    node_id: u8,
+    timeout: Duration,
+    next_id: AtomicU8,
+    pending: Mutex<HashMap<PendingKey, Waiter>>,
+    unsolicited: Option<mpsc::Sender<Frame>>,
+    backoff: Backoff,
+    retry: RetryPolicy,
+}
+
+/// Sends requests to the nodes on the bus and waits for their responses.
+///
+/// Every request gets an id, which the server copies into the response. A background task
+/// receives the frames, hands the responses to their requests, and the other frames to the
+/// `unsolicited` channel of the builder. The task stops when the client is dropped.
+pub struct TcspClient<D> {
+    inner: Arc<ClientInner<D>>,
+    receiver: JoinHandle<()>,
+}
+
+impl<D: DeviceAdaptor + 'static> TcspClient<D> {
+    fn new(inner: ClientInner<D>) -> Self {
+        let inner = Arc::new(inner);
+        let receiver = tokio::spawn(Arc::clone(&inner).receive());
+        Self { inner, receiver }
+    }
+
+    /// Send `frame` to node `dest`, and wait for the response with the timeout of the builder.
+    /// The request is sent again following the retry policy of the builder.
+    ///
+    /// An error response of the server is returned as `ClientError::Rejected`.
+    pub async fn request(&self, dest: u8, frame: Frame) -> Result<Frame, ClientError> {
+        self.request_timeout(dest, frame, self.inner.timeout).await
+    }
+
+    /// Like `request`, waiting `timeout` for the response to every attempt.
+    pub async fn request_timeout(
+        &self,
+        dest: u8,
+        frame: Frame,
+        timeout: Duration,
+    ) -> Result<Frame, ClientError> {
+        let reply = self.exchange(dest, frame, timeout, &self.inner.retry).await?;
+        Ok(reply.frame)
+    }
+
+    /// Like `request`, following `policy` instead of the retry policy of the builder, and
+    /// reporting how many attempts the request needed.
+    ///
+    /// A late response to an earlier attempt completes the request. The responses to the other
+    /// attempts, like those replayed by the duplicate cache of the server, are then unsolicited.
+    pub async fn request_with_retry(
+        &self,
+        dest: u8,
+        frame: Frame,
+        policy: &RetryPolicy,
+    ) -> Result<Reply, ClientError> {
+        self.exchange(dest, frame, self.inner.timeout, policy).await
+    }
+
+    async fn exchange(
+        &self,
+        dest: u8,
+        mut frame: Frame,
+        timeout: Duration,
+        policy: &RetryPolicy,
+    ) -> Result<Reply, ClientError> {
+        let (key, mut response) = self.inner.register(dest, frame.application(), || {
+            let (tx, rx) = oneshot::channel();
+            (Waiter::Single(tx), rx)
+        })?;
+        // unregisters the request however it ends
+        let _pending = Pending {
+            inner: &self.inner,
+            key,
+        };
+        self.inner.prepare(&mut frame, dest, key.id);
+        let bus_frame: BusFrame = frame.try_into()?;
+        let start = Instant::now();
+        let mut attempts = 0;
+        let response = loop {
+            attempts += 1;
+            let attempt_timeout = policy
+                .remaining(start.elapsed())
+                .map_or(timeout, |remaining| remaining.min(timeout));
+            match self.inner.adaptor.send(bus_frame.clone()).await {
+                Ok(()) => {
+                    // otherwise it timed out, the sender is only dropped with the request
+                    if let Ok(Ok(response)) =
+                        tokio::time::timeout(attempt_timeout, &mut response).await
+                    {
+                        break response;
+                    }
+                }
+                Err(e) if attempts >= policy.max_attempts() => {
+                    return Err(ClientError::Adaptor(e))
+                }
+                Err(e) => log::warn!("failed to send attempt {} to node {}: {}", attempts, dest, e),
+            }
+            let delay = policy.delay(attempts);
+            let exhausted = attempts >= policy.max_attempts()
+                || policy
+                    .remaining(start.elapsed())
+                    .is_some_and(|remaining| remaining <= delay);
+            if exhausted {
+                // a request sent once times out as without a policy
+                return Err(if policy.max_attempts() == 1 {
+                    ClientError::Timeout(attempt_timeout)
+                } else {
+                    ClientError::Unanswered {
+                        attempts,
+                        elapsed: start.elapsed(),
+                    }
+                });
+            }
+            tokio::time::sleep(delay).await;
+        };
+        if attempts > 1 {
+            log::debug!(
+                "request {} to node {} answered after {} attempts",
+                key.id,
+                dest,
+                attempts
+            );
+        }
+        if response.application() == ERROR_RESPONSE_APPLICATION_ID {
+            return Err(ClientError::Rejected(ErrorResponse::try_from(&response)?));
+        }
+        Ok(Reply {
+            frame: response,
+            attempts,
+        })
+    }
+
+    /// Send `frame` to node `dest`, and collect every frame of a multi-frame response, like
+    /// the one of `Diagnostics`.
+    ///
+    /// The first frame is awaited with the timeout of the builder. The protocol does not mark the
+    /// last frame of a response, so the response is complete once no frame arrives for `idle`,
+    /// which should exceed the pacing of the application. The request is sent once.
+    pub async fn request_frames(
+        &self,
+        dest: u8,
+        mut frame: Frame,
+        idle: Duration,
+    ) -> Result<Vec<Frame>, ClientError> {
+        let (key, mut frames) = self.inner.register(dest, frame.application(), || {
+            let (tx, rx) = mpsc::unbounded_channel();
+            (Waiter::Frames(tx), rx)
+        })?;
+        // keeps receiving the frames of the response until it is dropped
+        let _pending = Pending {
+            inner: &self.inner,
+            key,
+        };
+        self.inner.prepare(&mut frame, dest, key.id);
+        self.inner.send(frame).await?;
+        let timeout = self.inner.timeout;
+        let Ok(Some(first)) = tokio::time::timeout(timeout, frames.recv()).await else {
+            return Err(ClientError::Timeout(timeout));
+        };
+        if first.application() == ERROR_RESPONSE_APPLICATION_ID {
+            return Err(ClientError::Rejected(ErrorResponse::try_from(&first)?));
+        }
+        let mut response = vec![first];
+        while let Ok(Some(next)) = tokio::time::timeout(idle, frames.recv()).await {
+            response.push(next);
+        }
+        Ok(response)
+    }
+
+    /// Send `frame` to node `dest` without waiting for a response, as for broadcasts.
+    pub async fn send(&self, dest: u8, mut frame: Frame) -> Result<(), ClientError> {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        self.inner.prepare(&mut frame, dest, id);
+        self.inner.send(frame).await
+    }
+
+    /// The adaptor the client sends and receives on.
+    pub fn adaptor(&self) -> &D {
+        &self.inner.adaptor
+    }
+}
+
+impl<D> Drop for TcspClient<D> {
+    fn drop(&mut self) {
+        self.receiver.abort();
+    }
+}
+
+impl<D: DeviceAdaptor> ClientInner<D> {
+    /// Pick an id which no request to the same node and application is waiting with.
+    fn register<R>(
+        &self,
+        dest: u8,
+        application: u8,
+        waiter: impl FnOnce() -> (Waiter, R),
+    ) -> Result<(PendingKey, R), ClientError> {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        for _ in 0..=u8::MAX {
+            let key = PendingKey {
+                node: self.node(dest),
+                application,
+                id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            };
+            if let Entry::Vacant(entry) = pending.entry(key) {
+                let (waiter, receiver) = waiter();
+                entry.insert(waiter);
+                return Ok((key, receiver));
+            }
+        }
+        Err(ClientError::Busy { dest, application })
+    }
+
+    fn prepare(&self, frame: &mut Frame, dest: u8, id: u8) {
+        let meta = frame.meta_mut();
+        meta.src_id = self.node_id;
+        meta.dest_id = dest;
+        meta.id = id;
+    }
+
+    async fn send(&self, frame: Frame) -> Result<(), ClientError> {
+        let bus_frame: BusFrame = frame.try_into()?;
+        self.adaptor
+            .send(bus_frame)
+            .await
+            .map_err(ClientError::Adaptor)
+    }
+
+    async fn receive(self: Arc<Self>) {
+        let mut failures = 0;
+        loop {
+            let bus_frame = match self.adaptor.recv().await {
+                Ok(bus_frame) => bus_frame,
+                Err(DeviceAdaptorError::Empty | DeviceAdaptorError::FrameError(_)) => continue,
+                Err(e) => {
+                    failures += 1;
+                    let delay = self.backoff.delay(failures);
+                    log::error!("failed to receive, retry in {:?}: {}", delay, e);
+                    tokio::time::sleep(delay).await;
+                    if e.is_fatal() {
+                        if let Err(reopen_error) = self.adaptor.reopen().await {
+                            log::error!("failed to reopen the adaptor: {}", reopen_error);
+                        }
+                    }
+                    continue;
+                }
+            };
+            failures = 0;
+            match Frame::try_from(bus_frame) {
+                Ok(frame) => self.dispatch(frame),
+                Err(e) => log::warn!("drop a malformed frame: {}", e),
+            }
+        }
+    }
+
+    /// The node of a pending key, which the adaptor may not know.
+    fn node(&self, node: u8) -> u8 {
+        if self.adaptor.addressed() {
+            node
+        } else {
+            0
+        }
+    }
+
+    /// Hand a response to its request, or an unsolicited frame to the subscriber.
+    fn dispatch(&self, frame: Frame) {
+        let application = if frame.application() == ERROR_RESPONSE_APPLICATION_ID {
+            // the error response names the application of the failed request
+            frame.data().get(1).copied()
+        } else {
+            Some(frame.application())
+        };
+        let waiter = application.and_then(|application| {
+            let key = PendingKey {
+                node: self.node(frame.meta().src_id),
+                application,
+                id: frame.meta().id,
+            };
+            let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            match pending.get(&key)? {
+                Waiter::Single(_) => pending.remove(&key),
+                Waiter::Frames(frames) => Some(Waiter::Frames(frames.clone())),
+            }
+        });
+        // the request may have timed out meanwhile
+        let frame = match waiter {
+            Some(Waiter::Single(waiter)) => match waiter.send(frame) {
+                Ok(()) => return,
+                Err(frame) => frame,
+            },
+            Some(Waiter::Frames(frames)) => match frames.send(frame) {
+                Ok(()) => return,
+                Err(e) => e.0,
+            },
+            None => frame,
+        };
+        match &self.unsolicited {
+            Some(unsolicited) => {
+                if let Err(e) = unsolicited.try_send(frame) {
+                    log::warn!("drop an unsolicited frame: {}", e);
+                }
+            }
+            None => log::debug!(
+                "drop an unsolicited frame of application {}",
+                frame.application()
+            ),
+        }
+    }
+}
+
+/// Unregisters a request when it is answered, times out or is cancelled.
+struct Pending<'a, D> {
+    inner: &'a ClientInner<D>,
+    key: PendingKey,
+}
+
+impl<D> Drop for Pending<'_, D> {
+    fn drop(&mut self) {
+        self.inner
+            .pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.key);
+    }
+}
+
+pub struct TcspClientBuilder<D> {
+    adaptor: D,
+    node_id: u8,
+    timeout: Duration,
+    unsolicited: Option<mpsc::Sender<Frame>>,
+    backoff: Backoff,
+    retry: RetryPolicy,
+}
+
+impl<D: DeviceAdaptor + 'static> TcspClientBuilder<D> {
+    pub fn new(adaptor: D) -> Self {
+        Self {
+            adaptor,
+            node_id: DEFAULT_NODE_ID,
+            timeout: DEFAULT_TIMEOUT,
+            unsolicited: None,
+            backoff: Backoff::default(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Build the client and start receiving. Must be called within a tokio runtime.
+    pub fn build(self) -> TcspClient<D> {
+        TcspClient::new(ClientInner {
+            adaptor: self.adaptor,
+            node_id: self.node_id,
+            timeout: self.timeout,
+            next_id: AtomicU8::new(0),
+            pending: Mutex::new(HashMap::new()),
+            unsolicited: self.unsolicited,
+            backoff: self.backoff,
+            retry: self.retry,
+        })
+    }
+
+    /// The node id the requests are sent from. Default is 0, the on-board computer.
+    pub fn with_node_id(mut self, node_id: u8) -> Self {
+        self.node_id = node_id;
+        self
+    }
+
+    /// How long `TcspClient::request` waits for a response. Default is 5 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Where the received frames which answer no request go, like telemetry pushed by a node.
+    /// They are dropped if the channel is full. By default they are dropped.
+    pub fn with_unsolicited(mut self, unsolicited: mpsc::Sender<Frame>) -> Self {
+        self.unsolicited = Some(unsolicited);
+        self
+    }
+
+    /// How long to wait before receiving again from a failing adaptor. Same default as the server.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// How `TcspClient::request` and the typed requests are sent again when they are not
+    /// answered. By default a request is sent once.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+}
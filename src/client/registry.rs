@@ -0,0 +1,169 @@
+//! The built-in applications as a client sees them, to build command lines and completions
+//! without naming every application. Each application declares its `SPEC` next to its id, and a
+//! new application is listed here by adding its `SPEC` to `REGISTRY`.
+use std::{io, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    adaptor::DeviceAdaptor,
+    application::{Diagnostics, EchoCommand, Reboot, ResetNetwork, TeleMetry, TimeSync, UdpBackup},
+    protocol::Frame,
+};
+
+use super::{ClientError, TcspClient};
+
+/// A built-in application: its name, its id and the operations a client invokes on it.
+#[derive(Debug)]
+pub struct ApplicationSpec {
+    /// A short name in kebab case, like `time-sync`.
+    pub name: &'static str,
+    pub id: u8,
+    pub about: &'static str,
+    /// An application with a single unnamed operation is invoked by its name alone.
+    pub operations: &'static [OperationSpec],
+}
+
+/// One kind of request to an application, how it is built and how its response is decoded.
+#[derive(Debug)]
+pub struct OperationSpec {
+    /// `None` for the only operation of an application.
+    pub name: Option<&'static str>,
+    /// Empty for an unnamed operation, which its application describes.
+    pub about: &'static str,
+    pub arguments: &'static [ArgumentSpec],
+    pub answer: Answer,
+    /// Build the request frames from the values of `arguments`, in order.
+    pub request: fn(&[ArgumentValue]) -> io::Result<Vec<Frame>>,
+    /// Decode the response frames, given the values of `arguments`. Empty if not answered.
+    pub parse: fn(&[ArgumentValue], &[Frame]) -> io::Result<Decoded>,
+}
+
+/// How an operation is answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    /// The request frames are sent without waiting.
+    None,
+    Single,
+    /// Several frames, complete once none arrives for the given idle time.
+    Frames(Duration),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArgumentSpec {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: ArgumentKind,
+}
+
+/// How the value of an argument is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgumentKind {
+    /// Bytes written as text, or in hex when asked to.
+    Content,
+    /// Bytes written in hex.
+    Hex,
+    /// An optional time, now if not given.
+    Time,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgumentValue {
+    Bytes(Vec<u8>),
+    Time(DateTime<Utc>),
+}
+
+/// A decoded response, ready to be displayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decoded {
+    Bytes(Vec<u8>),
+    Text(String),
+    Number(u64),
+    List(Vec<Decoded>),
+    /// Named fields, in order.
+    Record(Vec<(&'static str, Decoded)>),
+}
+
+/// The built-in applications, by id. Each application describes itself with its `SPEC`.
+pub static REGISTRY: &[ApplicationSpec] = &[
+    TeleMetry::<()>::SPEC,
+    TimeSync::<()>::SPEC,
+    EchoCommand::SPEC,
+    Reboot::SPEC,
+    ResetNetwork::SPEC,
+    UdpBackup::<()>::SPEC,
+    Diagnostics::SPEC,
+];
+
+/// The built-in application named `name`.
+pub fn find_application(name: &str) -> Option<&'static ApplicationSpec> {
+    REGISTRY.iter().find(|application| application.name == name)
+}
+
+/// The name of the built-in application `id`.
+pub fn application_name(id: u8) -> Option<&'static str> {
+    REGISTRY
+        .iter()
+        .find(|application| application.id == id)
+        .map(|application| application.name)
+}
+
+impl<D: DeviceAdaptor + 'static> TcspClient<D> {
+    /// Invoke `operation` on node `dest` with the values of its arguments, and decode the response.
+    pub async fn invoke(
+        &self,
+        dest: u8,
+        operation: &OperationSpec,
+        arguments: &[ArgumentValue],
+    ) -> Result<Decoded, ClientError> {
+        let mut frames = (operation.request)(arguments)?;
+        let responses = match operation.answer {
+            Answer::None => {
+                for frame in frames {
+                    self.send(dest, frame).await?;
+                }
+                Vec::new()
+            }
+            Answer::Single | Answer::Frames(_) if frames.len() != 1 => {
+                return Err(ClientError::Frame(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "an answered operation has one request frame",
+                )))
+            }
+            Answer::Single => vec![self.request(dest, frames.remove(0)).await?],
+            Answer::Frames(idle) => self.request_frames(dest, frames.remove(0), idle).await?,
+        };
+        (operation.parse)(arguments, &responses).map_err(ClientError::Response)
+    }
+}
+
+pub(crate) fn single(frames: &[Frame]) -> io::Result<&Frame> {
+    frames
+        .first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no response frame"))
+}
+
+pub(crate) fn bytes(arguments: &[ArgumentValue], index: usize) -> io::Result<&[u8]> {
+    match arguments.get(index) {
+        Some(ArgumentValue::Bytes(bytes)) => Ok(bytes),
+        _ => Err(missing(index)),
+    }
+}
+
+pub(crate) fn time(arguments: &[ArgumentValue], index: usize) -> io::Result<DateTime<Utc>> {
+    match arguments.get(index) {
+        Some(ArgumentValue::Time(time)) => Ok(*time),
+        _ => Err(missing(index)),
+    }
+}
+
+pub(crate) fn missing(index: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("argument {} is missing or of another kind", index),
+    )
+}
+
+pub(crate) fn status(status: &str) -> Decoded {
+    Decoded::Record(vec![("status", Decoded::Text(status.to_owned()))])
+}
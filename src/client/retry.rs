@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use crate::server::Backoff;
+
+/// How a request which is not answered in time is sent again.
+///
+/// Every attempt sends the same frame with the same id, so a server deduplicating the application
+/// (`ApplicationOptions::dedup`) handles it once and replays its response. The delay between two
+/// attempts starts at the initial delay of the backoff and doubles with every attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Backoff,
+    deadline: Option<Duration>,
+}
+
+impl RetryPolicy {
+    /// Send a request up to `max_attempts` times, and at least once.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            backoff: Backoff::default(),
+            deadline: None,
+        }
+    }
+
+    /// The delay between two attempts. Same default as the server.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Give up once `deadline` has passed since the first attempt, even with attempts left.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// The delay before the attempt following `attempts` attempts.
+    pub(crate) fn delay(&self, attempts: u32) -> Duration {
+        self.backoff.delay(attempts)
+    }
+
+    /// How long is left until the deadline, after `elapsed`. `None` without a deadline.
+    pub(crate) fn remaining(&self, elapsed: Duration) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_sub(elapsed))
+    }
+}
+
+/// A request is sent once by default.
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::RetryPolicy;
+    use crate::server::Backoff;
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy::new(0);
+        assert_eq!(policy.max_attempts(), 1);
+        assert_eq!(policy.remaining(Duration::from_secs(100)), None);
+
+        let policy = RetryPolicy::new(4)
+            .with_backoff(Backoff::new(
+                Duration::from_millis(10),
+                Duration::from_secs(1),
+            ))
+            .with_deadline(Duration::from_millis(100));
+        assert_eq!(policy.delay(1), Duration::from_millis(10));
+        assert_eq!(policy.delay(3), Duration::from_millis(40));
+        assert_eq!(
+            policy.remaining(Duration::from_millis(30)),
+            Some(Duration::from_millis(70))
+        );
+        assert_eq!(
+            policy.remaining(Duration::from_millis(300)),
+            Some(Duration::ZERO)
+        );
+    }
+}
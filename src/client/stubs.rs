@@ -0,0 +1,67 @@
+//! Typed requests to the built-in applications.
+use chrono::{DateTime, Utc};
+
+use crate::{
+    adaptor::DeviceAdaptor,
+    application::{
+        Diagnostics, EchoCommand, NetworkStatus, Reboot, ResetNetwork, TeleMetry, TimeSync,
+        UdpBackup,
+    },
+    server::ServerStats,
+};
+
+use super::{ClientError, TcspClient};
+
+impl<D: DeviceAdaptor + 'static> TcspClient<D> {
+    /// Send `content` to the echo application of node `dest`, and return what it echoes.
+    pub async fn echo(&self, dest: u8, content: &[u8]) -> Result<Vec<u8>, ClientError> {
+        let response = self.request(dest, EchoCommand::request(content)?).await?;
+        Ok(response.data().to_vec())
+    }
+
+    /// The telemetry of node `dest`, as the opaque bytes its fallback answered with.
+    pub async fn telemetry(&self, dest: u8) -> Result<Vec<u8>, ClientError> {
+        let request = TeleMetry::<()>::request(self.inner.node_id, dest)?;
+        let response = self.request(dest, request).await?;
+        Ok(response.data().to_vec())
+    }
+
+    /// Set the clock of node `dest`. The time sync is not answered.
+    pub async fn sync_time(&self, dest: u8, datetime: DateTime<Utc>) -> Result<(), ClientError> {
+        self.send(dest, TimeSync::<()>::request(datetime)?).await
+    }
+
+    /// Reboot node `dest`, which acknowledges before it reboots.
+    pub async fn reboot(&self, dest: u8) -> Result<(), ClientError> {
+        let response = self.request(dest, Reboot::request()).await?;
+        Reboot::parse_response(&response).map_err(ClientError::Response)
+    }
+
+    /// The status of the network interfaces of node `dest`.
+    pub async fn list_network(&self, dest: u8) -> Result<NetworkStatus, ClientError> {
+        let response = self.request(dest, ResetNetwork::list_request()?).await?;
+        ResetNetwork::parse_list_response(&response).map_err(ClientError::Response)
+    }
+
+    /// Reapply the network configuration of node `dest`.
+    pub async fn reset_network(&self, dest: u8) -> Result<(), ClientError> {
+        let response = self.request(dest, ResetNetwork::reset_request()?).await?;
+        ResetNetwork::parse_reset_response(&response).map_err(ClientError::Response)
+    }
+
+    /// The counters of node `dest` and of its adaptors.
+    pub async fn diagnostics(&self, dest: u8) -> Result<ServerStats, ClientError> {
+        let frames = self
+            .request_frames(dest, Diagnostics::request(), Diagnostics::RESPONSE_IDLE)
+            .await?;
+        Diagnostics::parse_response(&frames).map_err(ClientError::Response)
+    }
+
+    /// Send a UDP command to node `dest`, split into several frames if needed. It is not answered.
+    pub async fn udp_backup(&self, dest: u8, command: &[u8]) -> Result<(), ClientError> {
+        for frame in UdpBackup::<()>::generate_request(command.to_vec(), dest)? {
+            self.send(dest, frame).await?;
+        }
+        Ok(())
+    }
+}
@@ -39,23 +39,37 @@
         clippy::shadow_unrelated,
         clippy::arithmetic_side_effects,
         clippy::let_underscore_untyped,
-        clippy::pedantic, 
+        clippy::pedantic,
         clippy::default_numeric_fallback,
         clippy::print_stderr,
     )
 )]
 
+extern crate self as tcsp;
+
 pub mod adaptor;
 mod application;
+mod client;
 mod protocol;
 mod server;
 #[cfg(test)]
 mod tests;
 mod utils;
 
-pub use adaptor::{DeviceAdaptor,TyCanProtocol,Uart};
-pub use server::TcspServerBuilder;
-pub use application::{EchoCommand, Reboot, TeleMetry, TimeSync,ZeromqSocket,UdpBackup,ResetNetwork};
-
-
-
+pub use adaptor::{AdaptorId, AdaptorStats, DeviceAdaptor, TyCanProtocol, Uart, Udp};
+pub use application::{
+    Application, ApplicationHealth, Diagnostics, EchoCommand, Health, HealthReport, NetworkFlag,
+    NetworkInterfaceStatus, NetworkStatus, Reboot, ResetNetwork, Response, TeleMetry, TimeSync,
+    UdpBackup, ZeromqSocket,
+};
+pub use client::{
+    application_name, find_application, Answer, ApplicationSpec, ArgumentKind, ArgumentSpec,
+    ArgumentValue, ClientError, Decoded, OperationSpec, Reply, RetryPolicy, TcspClient,
+    TcspClientBuilder, REGISTRY,
+};
+pub use protocol::{CodecError, Decode, Encode, ErrorResponse, ErrorStatus, Frame, Remaining};
+pub use server::{
+    AccessPolicy, ApplicationOptions, ApplicationTiming, Backoff, Downlink, Middleware, Next,
+    OverflowPolicy, Priority, RateLimit, RateLimitAction, RequestLogger, ServerError, ServerStats,
+    ShutdownHandle, TcspServer, TcspServerBuilder, TimingStats,
+};
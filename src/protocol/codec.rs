@@ -0,0 +1,257 @@
+//! Typed application payloads.
+//!
+//! Integers are big endian. `Vec<T>` and `String` are prefixed with their length as one byte,
+//! which is enough for any frame. `Remaining` takes every byte left at the end of a payload.
+//!
+//! Structs and fieldless enums derive both traits with `#[derive(Encode, Decode)]`. The fields
+//! of a struct are encoded in order, an enum is encoded as its discriminant, a `u8` unless the
+//! enum has another `#[repr]`.
+
+use std::io;
+
+pub use tcsp_derive::{Decode, Encode};
+
+/// A payload which could not be encoded or decoded.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CodecError {
+    #[error("payload too short, {expected} bytes expected but {remaining} left")]
+    Short { expected: usize, remaining: usize },
+    #[error("invalid {ty} value {value}")]
+    InvalidValue { ty: &'static str, value: u64 },
+    #[error("{0} trailing bytes after the payload")]
+    Trailing(usize),
+    #[error("{len} elements exceed the length prefix")]
+    TooLong { len: usize },
+}
+
+impl From<CodecError> for io::Error {
+    fn from(error: CodecError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error)
+    }
+}
+
+pub trait Encode {
+    /// Append the encoded value to `buf`.
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), CodecError>;
+
+    fn to_bytes(&self) -> Result<Vec<u8>, CodecError> {
+        let mut buf = Vec::new();
+        self.encode(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+pub trait Decode: Sized {
+    /// Decode a value from the front of `buf`, and advance `buf` past it.
+    fn decode(buf: &mut &[u8]) -> Result<Self, CodecError>;
+
+    /// Decode a value which spans the whole of `data`.
+    fn from_bytes(data: &[u8]) -> Result<Self, CodecError> {
+        let mut buf = data;
+        let value = Self::decode(&mut buf)?;
+        if !buf.is_empty() {
+            return Err(CodecError::Trailing(buf.len()));
+        }
+        Ok(value)
+    }
+}
+
+/// Split `len` bytes off the front of `buf`.
+pub(crate) fn take<'a>(buf: &mut &'a [u8], len: usize) -> Result<&'a [u8], CodecError> {
+    if buf.len() < len {
+        return Err(CodecError::Short {
+            expected: len,
+            remaining: buf.len(),
+        });
+    }
+    let (head, tail) = buf.split_at(len);
+    *buf = tail;
+    Ok(head)
+}
+
+macro_rules! impl_integer {
+    ($($ty:ty),*) => {$(
+        impl Encode for $ty {
+            fn encode(&self, buf: &mut Vec<u8>) -> Result<(), CodecError> {
+                buf.extend_from_slice(&self.to_be_bytes());
+                Ok(())
+            }
+        }
+
+        impl Decode for $ty {
+            fn decode(buf: &mut &[u8]) -> Result<Self, CodecError> {
+                let mut bytes = [0u8; std::mem::size_of::<$ty>()];
+                bytes.copy_from_slice(take(buf, std::mem::size_of::<$ty>())?);
+                Ok(<$ty>::from_be_bytes(bytes))
+            }
+        }
+    )*};
+}
+
+impl_integer!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl Encode for bool {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), CodecError> {
+        u8::from(*self).encode(buf)
+    }
+}
+
+impl Decode for bool {
+    fn decode(buf: &mut &[u8]) -> Result<Self, CodecError> {
+        match u8::decode(buf)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(CodecError::InvalidValue {
+                ty: "bool",
+                value: value.into(),
+            }),
+        }
+    }
+}
+
+impl<T: Encode, const N: usize> Encode for [T; N] {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), CodecError> {
+        self.iter().try_for_each(|item| item.encode(buf))
+    }
+}
+
+impl<T: Decode, const N: usize> Decode for [T; N] {
+    fn decode(buf: &mut &[u8]) -> Result<Self, CodecError> {
+        let items = (0..N)
+            .map(|_| T::decode(buf))
+            .collect::<Result<Vec<_>, _>>()?;
+        // never fails, `items` has exactly N elements
+        items.try_into().map_err(|_| CodecError::Short {
+            expected: N,
+            remaining: 0,
+        })
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), CodecError> {
+        let len = u8::try_from(self.len()).map_err(|_| CodecError::TooLong { len: self.len() })?;
+        len.encode(buf)?;
+        self.iter().try_for_each(|item| item.encode(buf))
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(buf: &mut &[u8]) -> Result<Self, CodecError> {
+        let len = u8::decode(buf)?;
+        (0..len).map(|_| T::decode(buf)).collect()
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), CodecError> {
+        let len = u8::try_from(self.len()).map_err(|_| CodecError::TooLong { len: self.len() })?;
+        len.encode(buf)?;
+        buf.extend_from_slice(self.as_bytes());
+        Ok(())
+    }
+}
+
+impl Decode for String {
+    fn decode(buf: &mut &[u8]) -> Result<Self, CodecError> {
+        let len = u8::decode(buf)?;
+        let bytes = take(buf, len.into())?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| CodecError::InvalidValue {
+            ty: "String",
+            value: len.into(),
+        })
+    }
+}
+
+/// The bytes at the end of a payload, without a length prefix. Only valid as the last field.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Remaining(pub Vec<u8>);
+
+impl Encode for Remaining {
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), CodecError> {
+        buf.extend_from_slice(&self.0);
+        Ok(())
+    }
+}
+
+impl Decode for Remaining {
+    fn decode(buf: &mut &[u8]) -> Result<Self, CodecError> {
+        Ok(Self(take(buf, buf.len())?.to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Decode, Encode};
+
+    use super::{CodecError, Remaining};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+    #[repr(u8)]
+    enum Kind {
+        Start = 1,
+        Data = 2,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+    #[repr(u16)]
+    enum Wide {
+        A = 0x0102,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Encode, Decode)]
+    struct Chunk {
+        kind: Kind,
+        id: u16,
+        flags: [u8; 2],
+        name: String,
+        data: Remaining,
+    }
+
+    #[derive(Debug, PartialEq, Eq, Encode, Decode)]
+    struct Pair(u8, Wide);
+
+    #[test]
+    fn test_codec_roundtrip() {
+        let chunk = Chunk {
+            kind: Kind::Data,
+            id: 0x1234,
+            flags: [0xa, 0xb],
+            name: "ab".to_owned(),
+            data: Remaining(vec![7, 8, 9]),
+        };
+        let bytes = chunk.to_bytes().unwrap();
+        assert_eq!(bytes, [2, 0x12, 0x34, 0xa, 0xb, 2, b'a', b'b', 7, 8, 9]);
+        assert_eq!(Chunk::from_bytes(&bytes).unwrap(), chunk);
+
+        let pair = Pair(3, Wide::A);
+        assert_eq!(pair.to_bytes().unwrap(), [3, 1, 2]);
+        assert_eq!(Pair::from_bytes(&[3, 1, 2]).unwrap(), pair);
+
+        assert_eq!(Vec::<u16>::from_bytes(&[2, 0, 1, 0, 2]).unwrap(), [1, 2]);
+        assert_eq!(
+            vec![0u8; 256].to_bytes(),
+            Err(CodecError::TooLong { len: 256 })
+        );
+    }
+
+    #[test]
+    fn test_codec_invalid() {
+        assert_eq!(
+            Chunk::from_bytes(&[2, 0x12]),
+            Err(CodecError::Short {
+                expected: 2,
+                remaining: 1
+            })
+        );
+        assert_eq!(
+            Kind::from_bytes(&[3]),
+            Err(CodecError::InvalidValue {
+                ty: "Kind",
+                value: 3
+            })
+        );
+        assert_eq!(u16::from_bytes(&[0, 1, 2]), Err(CodecError::Trailing(1)));
+        assert!(bool::from_bytes(&[2]).is_err());
+    }
+}
@@ -1,2 +1,5 @@
+pub mod codec;
 pub mod v1;
+pub use codec::{CodecError, Decode, Encode, Remaining};
+pub use v1::error_response::{ErrorResponse, ErrorStatus};
 pub use v1::frame::Frame;
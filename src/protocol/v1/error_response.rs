@@ -0,0 +1,141 @@
+use std::io;
+
+use num_enum::TryFromPrimitive;
+use thiserror::Error;
+
+use crate::adaptor::FrameMeta;
+
+use super::frame::Frame;
+
+/// The application id reserved for error responses.
+pub(crate) const ERROR_RESPONSE_APPLICATION_ID: u8 = 0xff;
+
+/// status(1B) and failing application id(1B)
+const ERROR_RESPONSE_HEADER_SIZE: usize = 2;
+
+/// Why the server could not answer a request.
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ErrorStatus {
+    UnknownApplication = 1,
+    VersionMismatch = 2,
+    HandlerError = 3,
+    Timeout = 4,
+    /// The requester sends faster than the server accepts.
+    RateLimited = 5,
+    /// The requester is not allowed to invoke the application.
+    PermissionDenied = 6,
+    /// The requests of an ordered application are queued up, the requester should retry later.
+    Busy = 7,
+}
+
+/// An error response sent back to the requester instead of the application response.
+///
+/// It is carried by a frame of application `0xff` with the payload `status(1B) | application(1B) | reason`.
+/// The reason is a short UTF-8 message, truncated to fit in the mtu.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{status:?} of application {application}:{reason}")]
+pub struct ErrorResponse {
+    pub status: ErrorStatus,
+    /// The application id of the failed request.
+    pub application: u8,
+    pub reason: String,
+}
+
+/// Returned by `Frame::try_from` when the version in the frame header is not supported.
+#[derive(Error, Debug)]
+#[error("Version ID not match, version={version:#x}")]
+pub(crate) struct VersionMismatch {
+    pub(crate) version: u8,
+    pub(crate) application: u8,
+}
+
+impl ErrorResponse {
+    pub fn new(status: ErrorStatus, application: u8, reason: impl Into<String>) -> Self {
+        Self {
+            status,
+            application,
+            reason: reason.into(),
+        }
+    }
+
+    /// Build the frame answering the request with meta `request`.
+    pub(crate) fn to_frame(&self, request: &FrameMeta, mtu: u16) -> io::Result<Frame> {
+        let max_reason_len = (mtu as usize).saturating_sub(ERROR_RESPONSE_HEADER_SIZE);
+        let reason = &self.reason.as_bytes()[..self.reason.len().min(max_reason_len)];
+        let mut frame = Frame::new(ERROR_RESPONSE_APPLICATION_ID);
+        frame.set_meta_from_request(request);
+        frame.set_len((ERROR_RESPONSE_HEADER_SIZE + reason.len()) as u16)?;
+        let data = frame.data_mut();
+        data[0] = self.status as u8;
+        data[1] = self.application;
+        data[ERROR_RESPONSE_HEADER_SIZE..].copy_from_slice(reason);
+        Ok(frame)
+    }
+}
+
+/// Returned by an application or a middleware, the error response is sent to the requester as it is.
+impl From<ErrorResponse> for io::Error {
+    fn from(error: ErrorResponse) -> Self {
+        io::Error::other(error)
+    }
+}
+
+impl TryFrom<&Frame> for ErrorResponse {
+    type Error = io::Error;
+
+    fn try_from(frame: &Frame) -> Result<Self, Self::Error> {
+        if frame.application() != ERROR_RESPONSE_APPLICATION_ID {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not an error response",
+            ));
+        }
+        let data = frame.data();
+        if data.len() < ERROR_RESPONSE_HEADER_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "error response too short",
+            ));
+        }
+        let status = ErrorStatus::try_from(data[0]).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown error status {}", data[0]),
+            )
+        })?;
+        Ok(Self {
+            status,
+            application: data[1],
+            reason: String::from_utf8_lossy(&data[ERROR_RESPONSE_HEADER_SIZE..]).into_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::adaptor::FrameMeta;
+
+    use super::{ErrorResponse, ErrorStatus, ERROR_RESPONSE_APPLICATION_ID};
+
+    #[test]
+    fn test_error_response_frame() {
+        let meta = FrameMeta {
+            src_id: 1,
+            dest_id: 2,
+            ..Default::default()
+        };
+        let error = ErrorResponse::new(ErrorStatus::HandlerError, 3, "something went wrong");
+        let frame = error.to_frame(&meta, 10).unwrap();
+        assert_eq!(frame.application(), ERROR_RESPONSE_APPLICATION_ID);
+        assert_eq!(frame.meta().src_id, 2);
+        assert_eq!(frame.meta().dest_id, 1);
+        assert_eq!(frame.data().len(), 10);
+
+        // the reason is truncated to the mtu
+        let parsed = ErrorResponse::try_from(&frame).unwrap();
+        assert_eq!(parsed.status, ErrorStatus::HandlerError);
+        assert_eq!(parsed.application, 3);
+        assert_eq!(parsed.reason, "somethin");
+    }
+}
@@ -1,6 +1,8 @@
 use std::{io, mem::size_of};
 
-use crate::adaptor::{Frame as BusFrame, FrameFlag, FrameMeta};
+use crate::adaptor::{AdaptorId, Frame as BusFrame, FrameFlag, FrameMeta};
+
+use super::error_response::VersionMismatch;
 
 
 pub(crate)const VERSION_ID: u8 = 0x20;
@@ -39,6 +41,7 @@ impl TryFrom<&mut [u8]> for &mut FrameHeader {
         unsafe { Ok(&mut *(buf.as_mut_ptr() as *mut FrameHeader)) }
     }
 }
+/// A frame of the TCSP protocol: the payload of a request or a response to one application.
 #[derive(Default, Debug)]
 pub struct Frame {
     bus_frame: BusFrame,
@@ -54,7 +57,10 @@ impl TryFrom<BusFrame> for Frame {
         if hdr.version != VERSION_ID {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidData,
-                "Version ID not match",
+                VersionMismatch {
+                    version: hdr.version,
+                    application: hdr.application,
+                },
             ));
         }
         bus_frame.shrink_head(size_of::<FrameHeader>())?;
@@ -75,7 +81,8 @@ impl TryFrom<Frame> for BusFrame {
 }
 
 impl Frame {
-    pub(crate) fn new(application_id: u8) -> Self {
+    /// An empty frame to `application_id`, grow it with `set_len`.
+    pub fn new(application_id: u8) -> Self {
         Self {
             bus_frame: BusFrame::default(),
             application_id,
@@ -83,7 +90,8 @@ impl Frame {
         }
     }
 
-    pub(crate) fn new_from_slice(application_id: u8,data: &[u8]) -> io::Result<Self> {
+    /// A frame to `application_id` carrying a copy of `data`.
+    pub fn new_from_slice(application_id: u8,data: &[u8]) -> io::Result<Self> {
         let bus_frame =  BusFrame::new(FrameMeta::default(),data)?;
         Ok(Self {
             bus_frame,
@@ -92,15 +100,20 @@ impl Frame {
         })
     }
 
-    pub(crate) fn application(&self) -> u8 {
+    pub fn application(&self) -> u8 {
         self.application_id
     }
 
-    pub(crate) fn data(&self) -> &[u8] {
+    pub fn data(&self) -> &[u8] {
         self.bus_frame.data()
     }
 
-    pub(crate) fn data_mut(&mut self) -> &mut [u8] {
+    /// The adaptor this frame was received from, for applications answering per adaptor.
+    pub fn adaptor(&self) -> AdaptorId {
+        self.bus_frame.meta.adaptor
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
         self.bus_frame.data_mut()
     }
 
@@ -111,11 +124,12 @@ impl Frame {
         Ok(())
     }
 
-    pub(crate) fn set_len(&mut self, len: u16) -> io::Result<()> {
+    /// Resize the payload.
+    pub fn set_len(&mut self, len: u16) -> io::Result<()> {
         self.bus_frame.set_len(len)
     }
 
-    pub(crate) fn meta(&self) -> &FrameMeta {
+    pub fn meta(&self) -> &FrameMeta {
         &self.bus_frame.meta
     }
 
@@ -129,7 +143,7 @@ impl Frame {
 
     /// Given a request frame, set the meta of the response frame.
     /// The source and destination of the response frame are exchanged.
-    pub(crate) fn set_meta_from_request(&mut self, meta: &FrameMeta) {
+    pub fn set_meta_from_request(&mut self, meta: &FrameMeta) {
         self.bus_frame.meta = *meta;
         self.bus_frame.meta.exchange_src_dest();
     }
@@ -143,6 +157,20 @@ fn insert_header(bus_frame: &mut BusFrame, application_id: u8) -> io::Result<()>
     Ok(())
 }
 
+/// The application a bus frame is addressed to, without parsing it.
+pub(crate) fn peek_application(frame: &BusFrame) -> Option<u8> {
+    let meta = &frame.meta;
+    if meta.flag.contains(FrameFlag::UartTelemetry) {
+        Some(0)
+    } else if meta.flag.contains(FrameFlag::CanTimeBroadcast) {
+        Some(1)
+    } else {
+        FrameHeader::try_from(frame.data())
+            .ok()
+            .map(|hdr| hdr.application)
+    }
+}
+
 fn install_header_if_needed(frame: &mut BusFrame) -> Result<(), io::Error> {
     let meta = &frame.meta;
     if meta.flag.contains(FrameFlag::UartTelemetry) {
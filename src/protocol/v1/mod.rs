@@ -1 +1,2 @@
-pub mod frame;
\ No newline at end of file
+pub mod error_response;
+pub mod frame;
@@ -0,0 +1,106 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    path::Path,
+    str::FromStr,
+};
+
+use serde::Deserialize;
+
+/// Which source nodes may invoke which applications.
+///
+/// An application without a rule accepts requests from every node. Loaded from TOML like:
+///
+/// ```toml
+/// # only the OBC may reboot
+/// [[rules]]
+/// application = 3
+/// sources = [0]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(from = "AccessPolicyConfig")]
+pub struct AccessPolicy {
+    rules: BTreeMap<u8, BTreeSet<u8>>,
+}
+
+#[derive(Deserialize)]
+struct AccessPolicyConfig {
+    #[serde(default)]
+    rules: Vec<AccessRule>,
+}
+
+#[derive(Deserialize)]
+struct AccessRule {
+    application: u8,
+    sources: Vec<u8>,
+}
+
+impl From<AccessPolicyConfig> for AccessPolicy {
+    fn from(config: AccessPolicyConfig) -> Self {
+        config.rules.into_iter().fold(Self::new(), |policy, rule| {
+            policy.allow(rule.application, rule.sources)
+        })
+    }
+}
+
+impl AccessPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Let `sources` invoke `application`, and nobody else unless allowed too.
+    pub fn allow(mut self, application: u8, sources: impl IntoIterator<Item = u8>) -> Self {
+        self.rules.entry(application).or_default().extend(sources);
+        self
+    }
+
+    pub fn is_allowed(&self, application: u8, src_id: u8) -> bool {
+        self.rules
+            .get(&application)
+            .is_none_or(|sources| sources.contains(&src_id))
+    }
+
+    /// Load the policy from a TOML file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        std::fs::read_to_string(path)?.parse()
+    }
+}
+
+impl FromStr for AccessPolicy {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AccessPolicy;
+
+    #[test]
+    fn test_access_policy_from_toml() {
+        let policy: AccessPolicy = r#"
+            [[rules]]
+            application = 2
+            sources = [0]
+
+            [[rules]]
+            application = 5
+            sources = [0, 0x10]
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(
+            policy,
+            AccessPolicy::new().allow(2, [0]).allow(5, [0, 0x10])
+        );
+        assert!(policy.is_allowed(2, 0));
+        assert!(!policy.is_allowed(2, 1));
+        assert!(policy.is_allowed(5, 0x10));
+        // no rule, no restriction
+        assert!(policy.is_allowed(3, 1));
+
+        assert!("rules = 1".parse::<AccessPolicy>().is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::adaptor::{AdaptorId, Frame as BusFrame, FrameMeta};
+
+/// Identifies a telecommand across its retransmissions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct DedupKey {
+    adaptor: AdaptorId,
+    src_id: u8,
+    application: u8,
+    id: u8,
+}
+
+impl DedupKey {
+    pub(crate) fn new(meta: &FrameMeta, application: u8) -> Self {
+        Self {
+            adaptor: meta.adaptor,
+            src_id: meta.src_id,
+            application,
+            id: meta.id,
+        }
+    }
+}
+
+/// What to do with a request, according to the requests seen before.
+pub(crate) enum Lookup<'a> {
+    /// A new request, which must be handled.
+    New(Pending<'a>),
+    /// A retransmission of a request still being handled. The first one will answer it.
+    InProgress,
+    /// A retransmission of a handled request. Replay the frames of its response.
+    Done(Vec<BusFrame>),
+}
+
+struct Entry {
+    since: Instant,
+    window: Duration,
+    /// `None` while the request is being handled.
+    response: Option<Vec<BusFrame>>,
+}
+
+/// The entry of a request being handled.
+///
+/// Dropped before it is finished, as when its handler fails or is aborted, it removes the entry,
+/// so a retransmission is handled again instead of being ignored.
+pub(crate) struct Pending<'a> {
+    cache: &'a DedupCache,
+    key: DedupKey,
+}
+
+impl Pending<'_> {
+    /// Record the response. The window restarts, as the requester only retransmits after missing it.
+    pub(crate) fn finish(self, response: Vec<BusFrame>) {
+        let mut entries = self.cache.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(entry) = entries.get_mut(&self.key) {
+            entry.since = Instant::now();
+            entry.response = Some(response);
+        }
+    }
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        let mut entries = self.cache.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if entries.get(&self.key).is_some_and(|entry| entry.response.is_none()) {
+            entries.remove(&self.key);
+        }
+    }
+}
+
+/// Remembers the recently handled telecommands and their responses.
+#[derive(Default)]
+pub(crate) struct DedupCache {
+    entries: Mutex<HashMap<DedupKey, Entry>>,
+}
+
+impl DedupCache {
+    /// Look up `key`, and mark it as being handled if it is new or its entry expired.
+    pub(crate) fn begin(&self, key: DedupKey, window: Duration) -> Lookup<'_> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        // a request still being handled is kept, however long its handler runs
+        entries.retain(|_, entry| {
+            entry.response.is_none() || now.duration_since(entry.since) < entry.window
+        });
+        if let Some(entry) = entries.get_mut(&key) {
+            return match &mut entry.response {
+                // the replayed frames share their buffers with the cached ones
+                Some(response) => {
+                    Lookup::Done(response.iter_mut().map(BusFrame::share).collect())
+                }
+                None => Lookup::InProgress,
+            };
+        }
+        entries.insert(
+            key,
+            Entry {
+                since: now,
+                window,
+                response: None,
+            },
+        );
+        Lookup::New(Pending { cache: self, key })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::adaptor::{Frame as BusFrame, FrameMeta};
+
+    use super::{DedupCache, DedupKey, Lookup};
+
+    #[test]
+    fn test_dedup_cache() {
+        let cache = DedupCache::default();
+        let meta = FrameMeta {
+            src_id: 1,
+            id: 7,
+            ..Default::default()
+        };
+        let key = DedupKey::new(&meta, 3);
+        let window = Duration::from_millis(50);
+        let Lookup::New(pending) = cache.begin(key, window) else {
+            panic!("the request is not new");
+        };
+        assert!(matches!(cache.begin(key, window), Lookup::InProgress));
+        // the handler outlives the window
+        std::thread::sleep(window);
+        assert!(matches!(cache.begin(key, window), Lookup::InProgress));
+
+        let response = BusFrame::new(meta, &[1, 2]).unwrap();
+        pending.finish(vec![response]);
+        let Lookup::Done(replay) = cache.begin(key, window) else {
+            panic!("the response is not replayed");
+        };
+        assert_eq!(replay.len(), 1);
+        assert_eq!(replay[0].data(), &[1, 2]);
+
+        // another id is a different telecommand
+        let other = DedupKey::new(&FrameMeta { id: 8, ..meta }, 3);
+        assert!(matches!(cache.begin(other, window), Lookup::New(_)));
+
+        std::thread::sleep(window);
+        assert!(matches!(cache.begin(key, window), Lookup::New(_)));
+    }
+
+    #[test]
+    fn test_dedup_unfinished() {
+        let cache = DedupCache::default();
+        let key = DedupKey::new(&FrameMeta::default(), 3);
+        let window = Duration::from_secs(1);
+        let Lookup::New(pending) = cache.begin(key, window) else {
+            panic!("the request is not new");
+        };
+        // the handler failed, or was aborted
+        drop(pending);
+        assert!(matches!(cache.begin(key, window), Lookup::New(_)));
+    }
+}
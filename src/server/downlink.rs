@@ -0,0 +1,110 @@
+use std::{
+    io,
+    mem::size_of,
+    sync::{Arc, Weak},
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    adaptor::{AdaptorId, Frame as BusFrame, FrameFlag},
+    protocol::{v1::frame::FrameHeader, Frame},
+};
+
+use super::ServerStats;
+
+/// The outbound side of a server, shared by the responses and the downlinks.
+#[async_trait]
+pub(crate) trait Egress: Send + Sync {
+    fn adaptors(&self) -> usize;
+
+    fn mtu(&self, adaptor: AdaptorId, flag: FrameFlag) -> Option<usize>;
+
+    /// Send `frame` through `adaptor`, after the frames queued before it.
+    async fn send(&self, adaptor: AdaptorId, frame: BusFrame) -> io::Result<()>;
+
+    fn stats(&self) -> ServerStats;
+}
+
+/// Lets an application send frames which do not answer a request, like periodic telemetry or events.
+///
+/// It is handed to `Application::init`. The frames are sent in turn with the responses,
+/// so they never interleave on the bus.
+#[derive(Clone)]
+pub struct Downlink {
+    application: u8,
+    egress: Weak<dyn Egress>,
+}
+
+impl Downlink {
+    pub(crate) fn new(application: u8, egress: Weak<dyn Egress>) -> Self {
+        Self {
+            application,
+            egress,
+        }
+    }
+
+    /// Send `frame` through every adaptor of the server.
+    pub async fn send(&self, frame: Frame) -> io::Result<()> {
+        let egress = self.egress()?;
+        let mut bus_frame = self.encode(frame)?;
+        for index in 0..egress.adaptors() {
+            let adaptor = AdaptorId(index);
+            check_mtu(egress.as_ref(), adaptor, &bus_frame)?;
+            egress.send(adaptor, bus_frame.share()).await?;
+        }
+        Ok(())
+    }
+
+    /// Send `frame` through `adaptor` only.
+    pub async fn send_to(&self, adaptor: AdaptorId, frame: Frame) -> io::Result<()> {
+        let egress = self.egress()?;
+        let bus_frame = self.encode(frame)?;
+        check_mtu(egress.as_ref(), adaptor, &bus_frame)?;
+        egress.send(adaptor, bus_frame).await
+    }
+
+    /// The counters of the server, for applications which report on it.
+    pub fn stats(&self) -> io::Result<ServerStats> {
+        Ok(self.egress()?.stats())
+    }
+
+    fn egress(&self) -> io::Result<Arc<dyn Egress>> {
+        self.egress
+            .upgrade()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "server is dropped"))
+    }
+
+    fn encode(&self, frame: Frame) -> io::Result<BusFrame> {
+        if frame.application() != self.application {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "application {} can not send frames of application {}",
+                    self.application,
+                    frame.application()
+                ),
+            ));
+        }
+        BusFrame::try_from(frame)
+    }
+}
+
+fn check_mtu(egress: &dyn Egress, adaptor: AdaptorId, frame: &BusFrame) -> io::Result<()> {
+    let mtu = egress
+        .mtu(adaptor, frame.meta.flag)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unknown adaptor"))?;
+    let len = frame.data().len();
+    if len > mtu {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "frame of {} bytes exceeds the mtu {} of adaptor {}",
+                len.saturating_sub(size_of::<FrameHeader>()),
+                mtu.saturating_sub(size_of::<FrameHeader>()),
+                adaptor.index()
+            ),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,23 @@
+use std::io;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ServerError {
+    #[error("Failed to init application {name}(id={id}):{source}")]
+    Init {
+        id: u8,
+        name: &'static str,
+        source: io::Error,
+    },
+
+    #[error("Duplicate application id of {first} and {second}, with same id {id}")]
+    DuplicateApplication {
+        id: u8,
+        first: &'static str,
+        second: &'static str,
+    },
+
+    #[error("Application {name} uses the reserved id {id}")]
+    ReservedApplication { id: u8, name: &'static str },
+}
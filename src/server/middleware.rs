@@ -0,0 +1,156 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    application::{Application, Response},
+    protocol::Frame,
+};
+
+/// A layer wrapped around `Application::handle`.
+///
+/// A middleware sees every request before the application does, and the response or error after it.
+/// It calls `next.run` to pass the request on, or returns without calling it to short-circuit.
+/// Returning an `io::Error` built from an `ErrorResponse` answers the requester with that error response.
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    async fn handle(&self, frame: Frame, mtu: u16, next: Next<'_>) -> io::Result<Response>;
+}
+
+/// The rest of the middleware chain, ending with the application.
+pub struct Next<'a> {
+    middlewares: &'a [Arc<dyn Middleware>],
+    application: &'a dyn Application,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        middlewares: &'a [Arc<dyn Middleware>],
+        application: &'a dyn Application,
+    ) -> Self {
+        Self {
+            middlewares,
+            application,
+        }
+    }
+
+    /// The application which handles the request at the end of the chain.
+    pub fn application(&self) -> &dyn Application {
+        self.application
+    }
+
+    pub async fn run(self, frame: Frame, mtu: u16) -> io::Result<Response> {
+        match self.middlewares.split_first() {
+            Some((middleware, rest)) => {
+                middleware
+                    .handle(frame, mtu, Next::new(rest, self.application))
+                    .await
+            }
+            None => self.application.handle(frame, mtu).await,
+        }
+    }
+}
+
+/// Log every request and the outcome of its handler.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestLogger;
+
+#[async_trait]
+impl Middleware for RequestLogger {
+    async fn handle(&self, frame: Frame, mtu: u16, next: Next<'_>) -> io::Result<Response> {
+        let name = next.application().application_name();
+        let meta = *frame.meta();
+        log::info!(
+            "request application={} src={:#x} dest={:#x} id={} len={}",
+            name,
+            meta.src_id,
+            meta.dest_id,
+            meta.id,
+            frame.data().len()
+        );
+        let result = next.run(frame, mtu).await;
+        match &result {
+            Ok(Response::Single(response)) => log::info!(
+                "response application={} len={}",
+                name,
+                response.data().len()
+            ),
+            Ok(response) => log::info!("response application={} {:?}", name, response),
+            Err(e) => log::warn!("response application={} error:{}", name, e),
+        }
+        result
+    }
+}
+
+/// The handling time of one application, collected by `ApplicationTiming`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimingStats {
+    pub requests: u64,
+    pub failures: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl TimingStats {
+    pub fn average(&self) -> Duration {
+        u32::try_from(self.requests)
+            .ok()
+            .and_then(|requests| self.total.checked_div(requests))
+            .unwrap_or_default()
+    }
+}
+
+/// Measure how long every application takes to handle a request.
+///
+/// Share it with `Arc` to read the stats while the server is running.
+#[derive(Debug, Default)]
+pub struct ApplicationTiming {
+    stats: Mutex<BTreeMap<u8, TimingStats>>,
+}
+
+impl ApplicationTiming {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The stats of the application `id`, if it has handled any request.
+    pub fn get(&self, id: u8) -> Option<TimingStats> {
+        self.lock().get(&id).copied()
+    }
+
+    /// The stats of every application which has handled a request, ordered by application id.
+    pub fn snapshot(&self) -> Vec<(u8, TimingStats)> {
+        self.lock()
+            .iter()
+            .map(|(id, stats)| (*id, *stats))
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<u8, TimingStats>> {
+        self.stats.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[async_trait]
+impl Middleware for ApplicationTiming {
+    async fn handle(&self, frame: Frame, mtu: u16, next: Next<'_>) -> io::Result<Response> {
+        let id = next.application().application_id();
+        let start = Instant::now();
+        let result = next.run(frame, mtu).await;
+        let elapsed = start.elapsed();
+        let mut stats = self.lock();
+        let entry = stats.entry(id).or_default();
+        entry.requests += 1;
+        if result.is_err() {
+            entry.failures += 1;
+        }
+        entry.total += elapsed;
+        entry.max = entry.max.max(elapsed);
+        result
+    }
+}
@@ -0,0 +1,1043 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::time::Duration;
+use std::sync::Weak;
+use std::{io, sync::Arc};
+
+use crate::adaptor::{AdaptorId, DeviceAdaptor, DeviceAdaptorError, Frame as BusFrame, FrameFlag, FrameMeta};
+
+mod access;
+mod backoff;
+mod dedup;
+mod downlink;
+mod error;
+mod middleware;
+mod queue;
+mod rate_limit;
+mod shutdown;
+mod stats;
+mod table;
+
+pub use access::AccessPolicy;
+pub use backoff::Backoff;
+pub use downlink::Downlink;
+pub use error::ServerError;
+pub use middleware::{ApplicationTiming, Middleware, Next, RequestLogger, TimingStats};
+pub use queue::{OverflowPolicy, Priority};
+pub use rate_limit::{RateLimit, RateLimitAction};
+pub use shutdown::ShutdownHandle;
+pub use stats::ServerStats;
+
+const DEFAULT_MAX_IN_FLIGHT: usize = 16;
+const DEFAULT_QUEUE_CAPACITY: usize = 64;
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct TcspServer<D>(Arc<TcspInner<D>>);
+
+use crate::application::{Application, ApplicationHealth, HealthReport, Response};
+use crate::protocol::v1::frame::{peek_application, FrameHeader};
+use crate::protocol::v1::error_response::VersionMismatch;
+use crate::protocol::{ErrorResponse, ErrorStatus, Frame};
+use dedup::{DedupCache, DedupKey, Lookup};
+use downlink::Egress;
+use queue::IngressQueue;
+use rate_limit::{BucketKey, RateLimiter};
+use stats::ServerCounters;
+use table::{ApplicationEntry, ApplicationTable};
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use futures_util::StreamExt;
+use tokio::sync::{mpsc::{self, error::TrySendError}, Semaphore};
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::{timeout, timeout_at, Instant};
+
+struct TcspInner<D> {
+    adaptors: Vec<D>,
+    /// Serialises the frames sent through each adaptor.
+    egress: Vec<tokio::sync::Mutex<()>>,
+    applications: ApplicationTable,
+    /// Whether the applications are initialized. Held while applications are initialized, shut down or registered.
+    running: tokio::sync::Mutex<bool>,
+    middlewares: Vec<Arc<dyn Middleware>>,
+    queue: IngressQueue<BusFrame>,
+    in_flight: Arc<Semaphore>,
+    shutdown: ShutdownHandle,
+    stats: ServerCounters,
+    dedup: DedupCache,
+    rate_limiter: RateLimiter,
+    config: ServerConfig,
+}
+
+/// Per application settings given at registration time.
+#[derive(Debug, Clone, Default)]
+pub struct ApplicationOptions {
+    ordered: bool,
+    timeout: Option<Duration>,
+    dedup_window: Option<Duration>,
+    pacing: Option<Duration>,
+    priority: Option<Priority>,
+    rate_limit: Option<RateLimit>,
+}
+
+impl ApplicationOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle the requests of this application one by one, in the order they were received.
+    ///
+    /// Stateful applications like `UploadCommand` need this, as concurrent handlers would interleave their state machine.
+    pub fn ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    /// Cancel a request of this application once it is handled longer than `timeout`,
+    /// and answer it with `ErrorStatus::Timeout`. The deadline also bounds a streamed response:
+    /// frames not produced in time are dropped, after those already sent. By default `Application::default_timeout` applies:
+    /// 100 milliseconds for the applications forwarding to a fallback, otherwise unbounded.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Detect retransmitted requests of this application, which have the same adaptor, `src_id` and `id`.
+    ///
+    /// Within `window` after a request is answered, its retransmissions get the cached response
+    /// instead of running the handler again. Non-idempotent applications like `Reboot` need this.
+    /// A request whose handler failed, timed out or whose response was not fully sent is handled again.
+    pub fn dedup(mut self, window: Duration) -> Self {
+        self.dedup_window = Some(window);
+        self
+    }
+
+    /// Wait `pacing` between the frames of a multi-frame response, for links which can not take them back to back.
+    pub fn pacing(mut self, pacing: Duration) -> Self {
+        self.pacing = Some(pacing);
+        self
+    }
+
+    /// The priority class of the requests of this application.
+    ///
+    /// By default time broadcasts are `Priority::High` and other requests are `Priority::Normal`.
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Limit the requests every source node sends to this application, on top of the limit of the server.
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ServerConfig {
+    max_in_flight: usize,
+    queue_capacity: usize,
+    overflow_policy: OverflowPolicy,
+    shutdown_timeout: Duration,
+    error_response_on_broadcast: bool,
+    rate_limit: Option<RateLimit>,
+    rate_limit_action: RateLimitAction,
+    access_policy: AccessPolicy,
+    backoff: Backoff,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            overflow_policy: OverflowPolicy::default(),
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            error_response_on_broadcast: false,
+            rate_limit: None,
+            rate_limit_action: RateLimitAction::default(),
+            access_policy: AccessPolicy::default(),
+            backoff: Backoff::default(),
+        }
+    }
+}
+
+impl<D: DeviceAdaptor> TcspServer<D> {
+    fn new(
+        adaptors: Vec<D>,
+        applications: impl Iterator<Item = (Arc<dyn Application>, ApplicationOptions)>,
+        middlewares: Vec<Arc<dyn Middleware>>,
+        config: ServerConfig,
+    ) -> Result<Self, ServerError> {
+        let application_table = ApplicationTable::new();
+        for (application, options) in applications {
+            let entry = ApplicationEntry::new(application, options);
+            application_table.check(&entry, false)?;
+            application_table.insert(entry);
+        }
+        Ok(TcspServer(Arc::new(TcspInner {
+            egress: adaptors.iter().map(|_| tokio::sync::Mutex::new(())).collect(),
+            adaptors,
+            applications: application_table,
+            running: tokio::sync::Mutex::new(false),
+            middlewares,
+            queue: IngressQueue::new(config.queue_capacity, config.overflow_policy),
+            in_flight: Arc::new(Semaphore::new(config.max_in_flight.max(1))),
+            shutdown: ShutdownHandle::new(),
+            stats: ServerCounters::default(),
+            dedup: DedupCache::default(),
+            rate_limiter: RateLimiter::default(),
+            config,
+        })))
+    }
+}
+
+impl<D: DeviceAdaptor + 'static> TcspServer<D> {
+    /// Receive frames from the adaptors and dispatch them to the applications.
+    ///
+    /// All adaptors share the same applications. A response is sent back through the adaptor its request came from.
+    ///
+    /// Frames are buffered in a bounded ingress queue and every request is handled in its own task,
+    /// so a slow application does not stall the others. At most `max_in_flight` handlers run at the same time.
+    ///
+    /// Every application is initialized before the first frame is accepted. If one of them fails,
+    /// the applications initialized so far are shut down and the error is returned.
+    ///
+    /// Returns once the server is stopped through its `ShutdownHandle`.
+    pub async fn listen(&self) -> Result<(), ServerError> {
+        {
+            let mut running = self.0.running.lock().await;
+            self.init_applications().await?;
+            *running = true;
+        }
+        log::info!("server start");
+        let receivers = self
+            .0
+            .adaptors
+            .iter()
+            .enumerate()
+            .map(|(index, adaptor)| self.receive(AdaptorId(index), adaptor));
+        tokio::join!(join_all(receivers), self.dispatch());
+        for adaptor in self.0.adaptors.iter() {
+            if let Err(e) = adaptor.flush().await {
+                log::error!("failed to flush adaptor:{}", e);
+            }
+        }
+        {
+            let mut running = self.0.running.lock().await;
+            *running = false;
+            self.shutdown_applications(self.0.applications.entries().iter())
+                .await;
+        }
+        log::info!("server stopped");
+        Ok(())
+    }
+
+    /// Query the health of every registered application.
+    pub async fn health(&self) -> HealthReport {
+        let mut report = HealthReport::default();
+        for entry in self.0.applications.entries() {
+            let application = &entry.application;
+            report.applications.push(ApplicationHealth {
+                id: application.application_id(),
+                name: application.application_name(),
+                health: application.health().await,
+            });
+        }
+        report
+    }
+
+    /// A handle to stop `listen` from another task.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.0.shutdown.clone()
+    }
+
+    /// The number of frames waiting in the ingress queue.
+    pub fn pending_frames(&self) -> usize {
+        self.0.queue.len()
+    }
+
+    /// The number of frames of `priority` waiting in the ingress queue.
+    pub fn pending_frames_of(&self, priority: Priority) -> usize {
+        self.0.queue.depth(priority)
+    }
+
+    /// The number of frames discarded because the ingress queue or the lane of an ordered application was full.
+    pub fn dropped_frames(&self) -> u64 {
+        self.0.queue.dropped()
+    }
+
+    /// The number of frames dropped or rejected for exceeding a rate limit.
+    pub fn rate_limited_frames(&self) -> u64 {
+        self.0.rate_limiter.limited()
+    }
+
+    /// The number of requests answered with `ErrorStatus::Timeout`.
+    pub fn timed_out_requests(&self) -> u64 {
+        self.0.stats.timeouts.get()
+    }
+
+    /// The counters of the server and of its adaptors since the server was built.
+    pub fn stats(&self) -> ServerStats {
+        self.0.stats()
+    }
+
+    /// Add an application to the server. Fails if its id is already taken.
+    ///
+    /// If the server is listening, the application is initialized before it receives any request.
+    pub async fn register(
+        &self,
+        application: Arc<dyn Application>,
+        options: ApplicationOptions,
+    ) -> Result<(), ServerError> {
+        let running = self.0.running.lock().await;
+        let entry = ApplicationEntry::new(application, options);
+        self.0.applications.check(&entry, false)?;
+        if *running {
+            self.init_application(&entry.application).await?;
+        }
+        log::info!(
+            "register application={}",
+            entry.application.application_name()
+        );
+        self.0.applications.insert(entry);
+        Ok(())
+    }
+
+    /// Add an application to the server, in place of the one with the same id. Returns the replaced application.
+    ///
+    /// The requests being handled by the replaced application are finished by it.
+    /// If the server is listening, the new application is initialized first, and the replaced one is shut down.
+    pub async fn replace(
+        &self,
+        application: Arc<dyn Application>,
+        options: ApplicationOptions,
+    ) -> Result<Option<Arc<dyn Application>>, ServerError> {
+        let running = self.0.running.lock().await;
+        let entry = ApplicationEntry::new(application, options);
+        self.0.applications.check(&entry, true)?;
+        if *running {
+            self.init_application(&entry.application).await?;
+        }
+        let previous = self.0.applications.insert(entry);
+        if *running {
+            self.shutdown_applications(previous.iter()).await;
+        }
+        Ok(previous.map(|replaced| replaced.application))
+    }
+
+    /// Remove the application `id` from the server, and return it.
+    ///
+    /// The requests being handled by it are finished. If the server is listening, the application is shut down.
+    pub async fn unregister(&self, id: u8) -> Option<Arc<dyn Application>> {
+        let running = self.0.running.lock().await;
+        let previous = self.0.applications.remove(id);
+        if *running {
+            self.shutdown_applications(previous.iter()).await;
+        }
+        previous.map(|entry| entry.application)
+    }
+
+    async fn init_applications(&self) -> Result<(), ServerError> {
+        let entries = self.0.applications.entries();
+        for (initialized, entry) in entries.iter().enumerate() {
+            if let Err(e) = self.init_application(&entry.application).await {
+                self.shutdown_applications(entries.iter().take(initialized))
+                    .await;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn init_application(&self, application: &Arc<dyn Application>) -> Result<(), ServerError> {
+        let egress: Weak<dyn Egress> = Arc::downgrade(&self.0) as _;
+        let downlink = Downlink::new(application.application_id(), egress);
+        application.init(downlink).await.map_err(|source| {
+            log::error!(
+                "failed to init application={}:{}",
+                application.application_name(),
+                source
+            );
+            ServerError::Init {
+                id: application.application_id(),
+                name: application.application_name(),
+                source,
+            }
+        })
+    }
+
+    async fn shutdown_applications(&self, entries: impl Iterator<Item = &ApplicationEntry>) {
+        for entry in entries {
+            let application = &entry.application;
+            if let Err(e) = application.shutdown().await {
+                log::error!(
+                    "failed to shutdown application={}:{}",
+                    application.application_name(),
+                    e
+                );
+            }
+        }
+    }
+
+    async fn receive(&self, adaptor_id: AdaptorId, adaptor: &D) {
+        let mut failures = 0;
+        loop {
+            let result = tokio::select! {
+                result = adaptor.recv() => result,
+                _ = self.0.shutdown.wait() => return,
+            };
+            let mut bus_frame = match result {
+                Ok(bus_frame) => bus_frame,
+                Err(DeviceAdaptorError::Empty | DeviceAdaptorError::FrameError(_)) => continue,
+                Err(e) => {
+                    failures += 1;
+                    if !self.recover(adaptor_id, adaptor, e, failures).await {
+                        return;
+                    }
+                    continue;
+                }
+            };
+            failures = 0;
+            self.0.stats.received.incr();
+            bus_frame.meta.adaptor = adaptor_id;
+            if !self.admit(&bus_frame).await {
+                continue;
+            }
+            let priority = self.classify(&bus_frame);
+            // The queue may be full and never drained again once the dispatcher stops.
+            tokio::select! {
+                _ = self.0.queue.push(bus_frame, priority) => {}
+                _ = self.0.shutdown.wait() => return,
+            }
+        }
+    }
+
+    /// Wait out the backoff after an adaptor error, and reopen the adaptor if the error is fatal.
+    ///
+    /// Returns false if the server stops meanwhile.
+    async fn recover(
+        &self,
+        adaptor_id: AdaptorId,
+        adaptor: &D,
+        error: DeviceAdaptorError,
+        failures: u32,
+    ) -> bool {
+        let delay = self.0.config.backoff.delay(failures);
+        log::error!(
+            "adaptor {:?} failed {} times in a row, retry in {:?}: {}",
+            adaptor_id,
+            failures,
+            delay,
+            error
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = self.0.shutdown.wait() => return false,
+        }
+        if error.is_fatal() {
+            match adaptor.reopen().await {
+                Ok(()) => log::info!("adaptor {:?} reopened", adaptor_id),
+                Err(e) => log::error!("failed to reopen adaptor {:?}: {}", adaptor_id, e),
+            }
+        }
+        true
+    }
+
+    /// Check a received frame against the rate limits of its source, and handle it if it is over them.
+    ///
+    /// This happens before the frame is queued, so a flooding node can not fill the ingress queue.
+    async fn admit(&self, bus_frame: &BusFrame) -> bool {
+        let meta = &bus_frame.meta;
+        let application = peek_application(bus_frame);
+        let application_limit = application
+            .and_then(|id| self.0.applications.get(id))
+            .and_then(|entry| entry.options.rate_limit);
+        let key = BucketKey {
+            adaptor: meta.adaptor,
+            src_id: meta.src_id,
+            application: None,
+        };
+        let mut limits = Vec::new();
+        if let Some(limit) = self.0.config.rate_limit {
+            limits.push((key, limit));
+        }
+        if let Some(limit) = application_limit {
+            limits.push((BucketKey { application, ..key }, limit));
+        }
+        if self.0.rate_limiter.admit(&limits) {
+            return true;
+        }
+        log::warn!(
+            "source={:#x} exceeds the rate limit, application={:?}",
+            meta.src_id,
+            application
+        );
+        if self.0.config.rate_limit_action == RateLimitAction::Reject {
+            let error = ErrorResponse::new(
+                ErrorStatus::RateLimited,
+                application.unwrap_or_default(),
+                "rate limited",
+            );
+            self.0.reply_error(meta, error).await;
+        }
+        false
+    }
+
+    /// The priority class of a received frame, by the setting of its application or by its flags.
+    ///
+    /// CAN reset frames never reach the queue, `TyCanProtocol` handles them as soon as they arrive.
+    fn classify(&self, bus_frame: &BusFrame) -> Priority {
+        let configured = peek_application(bus_frame)
+            .and_then(|id| self.0.applications.get(id))
+            .and_then(|entry| entry.options.priority);
+        match configured {
+            Some(priority) => priority,
+            None if bus_frame.meta.flag.contains(FrameFlag::CanTimeBroadcast) => Priority::High,
+            None => Priority::Normal,
+        }
+    }
+
+    async fn dispatch(&self) {
+        let mut handlers = JoinSet::new();
+        let mut lane_handles = Vec::new();
+        // The lanes of the ordered applications, spawned on their first request.
+        let mut lanes: HashMap<u8, mpsc::Sender<(ApplicationEntry, Frame)>> = HashMap::new();
+
+        loop {
+            while handlers.try_join_next().is_some() {}
+            let bus_frame = tokio::select! {
+                bus_frame = self.0.queue.pop() => bus_frame,
+                _ = self.0.shutdown.wait() => break,
+            };
+            let request_meta = bus_frame.meta;
+            let frame = match Frame::try_from(bus_frame) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    log::error!("Error occurs:{:?}", e);
+                    let mismatch = e.get_ref().and_then(|e| e.downcast_ref::<VersionMismatch>());
+                    if let Some(mismatch) = mismatch {
+                        self.0.stats.version_mismatches.incr();
+                        let error = ErrorResponse::new(
+                            ErrorStatus::VersionMismatch,
+                            mismatch.application,
+                            mismatch.to_string(),
+                        );
+                        self.0.reply_error(&request_meta, error).await;
+                    } else {
+                        self.0.stats.malformed.incr();
+                    }
+                    continue;
+                }
+            };
+            let application_id = frame.application();
+            let Some(entry) = self.0.applications.get(application_id) else {
+                self.0.stats.unknown_applications.incr();
+                log::error!("application={} not found", application_id);
+                let error = ErrorResponse::new(
+                    ErrorStatus::UnknownApplication,
+                    frame.application(),
+                    "application not found",
+                );
+                self.0.reply_error(frame.meta(), error).await;
+                continue;
+            };
+            log::info!("receive application={}", entry.application.application_name());
+            let src_id = frame.meta().src_id;
+            if !self.0.config.access_policy.is_allowed(application_id, src_id) {
+                self.0.stats.permission_denied.incr();
+                log::warn!(
+                    "source={:#x} is not allowed to invoke application={}",
+                    src_id,
+                    entry.application.application_name()
+                );
+                let error = ErrorResponse::new(
+                    ErrorStatus::PermissionDenied,
+                    application_id,
+                    "permission denied",
+                );
+                self.0.reply_error(frame.meta(), error).await;
+                continue;
+            }
+
+            if entry.options.ordered {
+                let lane = lanes.entry(application_id).or_insert_with(|| {
+                    let (sender, handle) = self.spawn_lane();
+                    lane_handles.push(handle);
+                    sender
+                });
+                // a full lane must not hold up the requests of the other applications
+                match lane.try_send((entry, frame)) {
+                    Ok(()) => self.0.stats.handled.incr(),
+                    Err(TrySendError::Full((_, frame))) => {
+                        self.0.lane_full(application_id, &frame).await;
+                    }
+                    Err(TrySendError::Closed(_)) => {
+                        log::error!("application={} lane closed", application_id);
+                    }
+                }
+                continue;
+            }
+            self.0.stats.handled.incr();
+
+            // every permit may be held by a hung handler
+            let permit = tokio::select! {
+                permit = Arc::clone(&self.0.in_flight).acquire_owned() => permit,
+                _ = self.0.shutdown.wait() => break,
+            };
+            let Ok(permit) = permit else {
+                break;
+            };
+            let server = Arc::clone(&self.0);
+            handlers.spawn(async move {
+                if let Err(e) = server.handle(entry.application, &entry.options, frame).await {
+                    log::error!("Error occurs:{:?}", e);
+                }
+                drop(permit);
+            });
+        }
+
+        let pending = self.0.queue.len();
+        if pending > 0 {
+            log::warn!("server stopping, {} queued frames are discarded", pending);
+        }
+        // Closing the lanes lets them exit once their queued requests are handled.
+        drop(lanes);
+        let drain = async {
+            while handlers.join_next().await.is_some() {}
+            for handle in lane_handles.iter_mut() {
+                let _ = handle.await;
+            }
+        };
+        if timeout(self.0.config.shutdown_timeout, drain).await.is_err() {
+            log::warn!(
+                "{} handlers still running after {:?}, abort them",
+                handlers.len(),
+                self.0.config.shutdown_timeout
+            );
+            handlers.abort_all();
+            for handle in lane_handles.iter() {
+                handle.abort();
+            }
+        }
+    }
+
+    /// Spawn a task which handles the requests of an ordered application sequentially.
+    ///
+    /// Every request carries its entry, so a replaced application still finishes the requests queued before.
+    fn spawn_lane(&self) -> (mpsc::Sender<(ApplicationEntry, Frame)>, JoinHandle<()>) {
+        let (tx, mut rx) = mpsc::channel(self.0.config.queue_capacity.max(1));
+        let server = Arc::clone(&self.0);
+        let handle = tokio::spawn(async move {
+            while let Some((entry, frame)) = rx.recv().await {
+                let Ok(_permit) = server.in_flight.acquire().await else {
+                    return;
+                };
+                let ApplicationEntry {
+                    application,
+                    options,
+                } = entry;
+                if let Err(e) = server.handle(application, &options, frame).await
+                {
+                    log::error!("Error occurs:{:?}", e);
+                }
+            }
+        });
+        (tx, handle)
+    }
+}
+
+impl<D: DeviceAdaptor> TcspInner<D> {
+    /// Apply the overflow policy to a request of an ordered application whose lane is full.
+    ///
+    /// With `OverflowPolicy::Backpressure` the request is answered with `ErrorStatus::Busy`,
+    /// otherwise it is dropped. Either way it counts as a dropped frame.
+    async fn lane_full(&self, application_id: u8, frame: &Frame) {
+        match self.config.overflow_policy {
+            OverflowPolicy::Backpressure => {
+                self.queue.drop_one("ordered lane full, answer the request busy");
+                let error = ErrorResponse::new(
+                    ErrorStatus::Busy,
+                    application_id,
+                    "too many queued requests",
+                );
+                self.reply_error(frame.meta(), error).await;
+            }
+            OverflowPolicy::DropNewest | OverflowPolicy::DropOldest => {
+                self.queue.drop_one("ordered lane full, drop the incoming frame");
+            }
+        }
+    }
+
+    async fn handle(
+        &self,
+        application: Arc<dyn Application>,
+        options: &ApplicationOptions,
+        frame: Frame,
+    ) -> Result<(), io::Error> {
+        let adaptor_id = frame.adaptor();
+        let Some(adaptor) = self.adaptors.get(adaptor_id.index()) else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "unknown adaptor"));
+        };
+        let dedup = options
+            .dedup_window
+            .map(|window| (DedupKey::new(frame.meta(), frame.application()), window));
+        let mut pending = None;
+        if let Some((key, window)) = dedup {
+            match self.dedup.begin(key, window) {
+                Lookup::New(entry) => pending = Some(entry),
+                Lookup::InProgress => {
+                    log::info!(
+                        "application={} ignore retransmitted request id={}, it is being handled",
+                        application.application_name(),
+                        frame.meta().id
+                    );
+                    return Ok(());
+                }
+                Lookup::Done(response) => {
+                    log::info!(
+                        "application={} replay the response of retransmitted request id={}",
+                        application.application_name(),
+                        frame.meta().id
+                    );
+                    for (index, cached) in response.into_iter().enumerate() {
+                        self.pace(index, options.pacing).await;
+                        if let Err(e) = self.send_frame(adaptor_id, cached).await {
+                            log::error!("faild to send application response:{}", e);
+                            break;
+                        }
+                    }
+                    return Ok(());
+                }
+            }
+        }
+        let mtu = adaptor.mtu(frame.meta().flag);
+        let deadline = options.timeout.or_else(|| application.default_timeout());
+        // a streamed response is produced while it is sent, so the deadline covers both
+        let expiry = deadline.map(|deadline| Instant::now() + deadline);
+        let request_meta = *frame.meta();
+        let (response, replayable) =
+            match self.respond(application.as_ref(), deadline, frame, mtu).await {
+                Ok(response) => (response, true),
+                // a retransmission retries a failed handler, rather than replaying the failure
+                Err(error) => {
+                    let replayable =
+                        !matches!(error.status, ErrorStatus::Timeout | ErrorStatus::HandlerError);
+                    (self.error_frame(&request_meta, error).into(), replayable)
+                }
+            };
+        let sent = self
+            .send_response(adaptor_id, response, mtu, options.pacing, pending.is_some(), expiry)
+            .await;
+        // an entry dropped unfinished is removed, so a retransmission is handled again
+        if let (Some(pending), Some(sent)) = (pending, sent) {
+            if replayable {
+                pending.finish(sent);
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the middlewares and the application. A failure is returned as the error response to send.
+    async fn respond(
+        &self,
+        application: &dyn Application,
+        deadline: Option<Duration>,
+        frame: Frame,
+        mtu: usize,
+    ) -> Result<Response, ErrorResponse> {
+        let application_id = frame.application();
+        let payload_mtu = (mtu - size_of::<FrameHeader>()) as u16;
+        let handling = Next::new(&self.middlewares, application).run(frame, payload_mtu);
+        let result = match deadline {
+            Some(deadline) => timeout(deadline, handling).await.unwrap_or_else(|_| {
+                Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("handler exceeded {:?}", deadline),
+                ))
+            }),
+            None => handling.await,
+        };
+        match result {
+            Ok(response) => {
+                log::debug!("response:{:?}", response);
+                Ok(response)
+            }
+            Err(e) => {
+                log::error!(
+                    "application={} failed:{}",
+                    application.application_name(),
+                    e
+                );
+                let error = match e.get_ref().and_then(|e| e.downcast_ref::<ErrorResponse>()) {
+                    Some(error) => error.clone(),
+                    None if e.kind() == io::ErrorKind::TimedOut => {
+                        ErrorResponse::new(ErrorStatus::Timeout, application_id, e.to_string())
+                    }
+                    None => {
+                        ErrorResponse::new(ErrorStatus::HandlerError, application_id, e.to_string())
+                    }
+                };
+                self.stats.handler_errors.incr();
+                if error.status == ErrorStatus::Timeout {
+                    self.stats.timeouts.incr();
+                }
+                Err(error)
+            }
+        }
+    }
+
+    /// Send the frames of `response` in order. Returns the sent frames if `keep` is set,
+    /// which share their buffers with the frames handed to the adaptor.
+    ///
+    /// The response ends early if a frame can not be built or exceeds the `mtu`. It is cut short,
+    /// and `None` is returned, if a frame fails to be sent or is not produced before `expiry`,
+    /// which counts as a timeout.
+    async fn send_response(
+        &self,
+        adaptor: AdaptorId,
+        response: Response,
+        mtu: usize,
+        pacing: Option<Duration>,
+        keep: bool,
+        expiry: Option<Instant>,
+    ) -> Option<Vec<BusFrame>> {
+        let mut sent = Vec::new();
+        let mut frames = response.into_stream().enumerate();
+        loop {
+            let next = match expiry {
+                Some(expiry) => match timeout_at(expiry, frames.next()).await {
+                    Ok(next) => next,
+                    Err(_) => {
+                        log::error!("application response stream exceeded its deadline");
+                        self.stats.timeouts.incr();
+                        return None;
+                    }
+                },
+                None => frames.next().await,
+            };
+            let Some((index, frame)) = next else {
+                break;
+            };
+            let mut frame = match frame.and_then(BusFrame::try_from) {
+                Ok(frame) => frame,
+                Err(e) => {
+                    log::error!("failed to build application response:{}", e);
+                    break;
+                }
+            };
+            if frame.data().len() > mtu {
+                log::error!(
+                    "application response of {} bytes exceeds the mtu {}",
+                    frame.data().len(),
+                    mtu
+                );
+                break;
+            }
+            self.pace(index, pacing).await;
+            let kept = keep.then(|| frame.share());
+            if let Err(e) = self.send_frame(adaptor, frame).await {
+                log::error!("faild to send application response:{}", e);
+                return None;
+            }
+            sent.extend(kept);
+        }
+        Some(sent)
+    }
+
+    /// Wait between the frames of a response, not before the first one.
+    async fn pace(&self, index: usize, pacing: Option<Duration>) {
+        if let Some(pacing) = pacing.filter(|_| index > 0) {
+            tokio::time::sleep(pacing).await;
+        }
+    }
+
+    /// Tell the requester why its request could not be answered.
+    async fn reply_error(&self, request: &FrameMeta, error: ErrorResponse) {
+        let Some(frame) = self.error_frame(request, error) else {
+            return;
+        };
+        let result = match BusFrame::try_from(frame) {
+            Ok(frame) => self.send_frame(request.adaptor, frame).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            log::error!("faild to send error response:{}", e);
+        }
+    }
+
+    /// Send a frame through `adaptor`, waiting for the frames sent before it to leave first.
+    async fn send_frame(&self, adaptor: AdaptorId, frame: BusFrame) -> Result<(), DeviceAdaptorError> {
+        let (Some(device), Some(lock)) = (
+            self.adaptors.get(adaptor.index()),
+            self.egress.get(adaptor.index()),
+        ) else {
+            return Err(DeviceAdaptorError::FrameError("unknown adaptor".to_owned()));
+        };
+        let _guard = lock.lock().await;
+        device.send(frame).await
+    }
+
+    fn stats(&self) -> ServerStats {
+        let counters = &self.stats;
+        ServerStats {
+            received: counters.received.get(),
+            rate_limited: self.rate_limiter.limited(),
+            dropped: self.queue.dropped(),
+            malformed: counters.malformed.get(),
+            version_mismatches: counters.version_mismatches.get(),
+            unknown_applications: counters.unknown_applications.get(),
+            permission_denied: counters.permission_denied.get(),
+            handled: counters.handled.get(),
+            handler_errors: counters.handler_errors.get(),
+            timeouts: counters.timeouts.get(),
+            adaptors: self.adaptors.iter().map(DeviceAdaptor::stats).collect(),
+        }
+    }
+
+    /// Build the error response of `request`, unless it is a broadcast which is not answered.
+    fn error_frame(&self, request: &FrameMeta, error: ErrorResponse) -> Option<Frame> {
+        if request.is_broadcast() && !self.config.error_response_on_broadcast {
+            return None;
+        }
+        let adaptor = self.adaptors.get(request.adaptor.index())?;
+        let mtu = (adaptor.mtu(request.flag) - size_of::<FrameHeader>()) as u16;
+        error
+            .to_frame(request, mtu)
+            .map_err(|e| log::error!("failed to build error response:{}", e))
+            .ok()
+    }
+}
+
+#[async_trait]
+impl<D: DeviceAdaptor> Egress for TcspInner<D> {
+    fn adaptors(&self) -> usize {
+        self.adaptors.len()
+    }
+
+    fn mtu(&self, adaptor: AdaptorId, flag: FrameFlag) -> Option<usize> {
+        self.adaptors.get(adaptor.index()).map(|device| device.mtu(flag))
+    }
+
+    async fn send(&self, adaptor: AdaptorId, frame: BusFrame) -> io::Result<()> {
+        self.send_frame(adaptor, frame)
+            .await
+            .map_err(|e| io::Error::other(e.to_string()))
+    }
+
+    fn stats(&self) -> ServerStats {
+        TcspInner::stats(self)
+    }
+}
+
+pub struct TcspServerBuilder<A> {
+    adaptors: Vec<A>,
+    applications: Vec<(Arc<dyn Application>, ApplicationOptions)>,
+    middlewares: Vec<Arc<dyn Middleware>>,
+    config: ServerConfig,
+}
+
+impl<A: DeviceAdaptor> TcspServerBuilder<A> {
+    pub fn new(adaptor: A) -> Self {
+        Self {
+            adaptors: vec![adaptor],
+            applications: Vec::new(),
+            middlewares: Vec::new(),
+            config: ServerConfig::default(),
+        }
+    }
+
+    /// Build the server. Fails if two applications share the same application id.
+    pub fn build(self) -> Result<TcspServer<A>, ServerError> {
+        TcspServer::new(
+            self.adaptors,
+            self.applications.into_iter(),
+            self.middlewares,
+            self.config,
+        )
+    }
+
+    /// Listen on one more adaptor. Use `Box<dyn DeviceAdaptor>` or `Arc<dyn DeviceAdaptor>` to mix different kinds of adaptors.
+    ///
+    /// The adaptor given to `new` gets `AdaptorId` 0, and the following ones are numbered in the order they are added.
+    pub fn with_adaptor(mut self, adaptor: A) -> Self {
+        self.adaptors.push(adaptor);
+        self
+    }
+
+    pub fn with_application(self, application: Arc<dyn Application>) -> Self {
+        self.with_application_options(application, ApplicationOptions::default())
+    }
+
+    pub fn with_application_options(
+        mut self,
+        application: Arc<dyn Application>,
+        options: ApplicationOptions,
+    ) -> Self {
+        self.applications.push((application, options));
+        self
+    }
+
+    /// Wrap every application handler in `middleware`.
+    ///
+    /// The middleware added first is the outermost one, so it sees the request first and the response last.
+    pub fn with_middleware(mut self, middleware: Arc<dyn Middleware>) -> Self {
+        self.middlewares.push(middleware);
+        self
+    }
+
+    /// The maximum number of application handlers running at the same time. Default is 16.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.config.max_in_flight = max_in_flight;
+        self
+    }
+
+    /// The number of received frames buffered while all handlers are busy. Default is 64.
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        self.config.queue_capacity = capacity;
+        self
+    }
+
+    /// What to do with received frames once the ingress queue is full. Default is `OverflowPolicy::Backpressure`.
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.config.overflow_policy = policy;
+        self
+    }
+
+    /// How long a stopping server waits for in-flight handlers before aborting them. Default is 5 seconds.
+    pub fn with_shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.config.shutdown_timeout = shutdown_timeout;
+        self
+    }
+
+    /// Limit the frames every source node sends, by its `src_id`. Default is unlimited.
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.config.rate_limit = Some(limit);
+        self
+    }
+
+    /// What to do with the frames over a rate limit. Default is `RateLimitAction::Drop`.
+    pub fn with_rate_limit_action(mut self, action: RateLimitAction) -> Self {
+        self.config.rate_limit_action = action;
+        self
+    }
+
+    /// How long to wait before receiving again from a failing adaptor. Default starts at 100
+    /// milliseconds and doubles up to 10 seconds.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.config.backoff = backoff;
+        self
+    }
+
+    /// Restrict which source nodes may invoke which applications. Default allows everything.
+    pub fn with_access_policy(mut self, policy: AccessPolicy) -> Self {
+        self.config.access_policy = policy;
+        self
+    }
+
+    /// Whether a failed broadcast request is answered with an error response. Default is false,
+    /// as every node on the bus would answer the same broadcast.
+    pub fn with_error_response_on_broadcast(mut self, enable: bool) -> Self {
+        self.config.error_response_on_broadcast = enable;
+        self
+    }
+}
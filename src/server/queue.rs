@@ -0,0 +1,221 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, PoisonError,
+    },
+};
+
+use tokio::sync::Notify;
+
+/// What the server does with a received frame when the ingress queue is full.
+///
+/// It also applies when the lane of an ordered application is full, where waiting would hold up
+/// every other application: `Backpressure` answers the request with `ErrorStatus::Busy` instead,
+/// and both drop policies discard the request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Stop reading from the adaptor until the dispatcher frees a slot.
+    #[default]
+    Backpressure,
+    /// Discard the frame that just arrived.
+    DropNewest,
+    /// Discard the oldest queued frame to make room for the new one.
+    ///
+    /// The frame is taken from the lowest priority class, and the new frame is discarded instead
+    /// if every queued frame has a higher priority.
+    DropOldest,
+}
+
+/// The class of a received frame. When a backlog exists, higher classes are dispatched first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Time broadcasts and emergency commands.
+    High,
+    #[default]
+    Normal,
+    /// Bulk transfers like upload chunks.
+    Low,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Low];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// A bounded queue between the adaptor receiver and the dispatcher, FIFO within a priority class.
+pub(crate) struct IngressQueue<T> {
+    classes: Mutex<[VecDeque<T>; Priority::ALL.len()]>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    not_empty: Notify,
+    not_full: Notify,
+    dropped: AtomicU64,
+}
+
+impl<T> IngressQueue<T> {
+    pub(crate) fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        Self {
+            classes: Mutex::new(core::array::from_fn(|_| VecDeque::new())),
+            capacity: capacity.max(1),
+            policy,
+            not_empty: Notify::new(),
+            not_full: Notify::new(),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Push an item, applying the overflow policy when the queue is full.
+    ///
+    /// Only returns after the item is queued or dropped, so with `OverflowPolicy::Backpressure`
+    /// this future stays pending as long as the dispatcher is behind.
+    pub(crate) async fn push(&self, item: T, priority: Priority) {
+        loop {
+            let notified = self.not_full.notified();
+            {
+                let mut classes = self.lock();
+                if classes.iter().map(VecDeque::len).sum::<usize>() < self.capacity {
+                    classes[priority.index()].push_back(item);
+                    break;
+                }
+                match self.policy {
+                    OverflowPolicy::DropNewest => {
+                        self.drop_one("ingress queue full, drop the incoming frame");
+                        return;
+                    }
+                    OverflowPolicy::DropOldest => {
+                        let lowest = classes[priority.index()..]
+                            .iter_mut()
+                            .rev()
+                            .find(|class| !class.is_empty());
+                        let Some(lowest) = lowest else {
+                            self.drop_one(
+                                "ingress queue full of higher priority, drop the incoming frame",
+                            );
+                            return;
+                        };
+                        let _oldest = lowest.pop_front();
+                        classes[priority.index()].push_back(item);
+                        self.drop_one("ingress queue full, drop the oldest frame");
+                        break;
+                    }
+                    OverflowPolicy::Backpressure => {}
+                }
+            }
+            notified.await;
+        }
+        self.not_empty.notify_one();
+    }
+
+    /// Pop the oldest item of the highest priority class, waiting until one is available.
+    pub(crate) async fn pop(&self) -> T {
+        loop {
+            let notified = self.not_empty.notified();
+            let item = self.lock().iter_mut().find_map(VecDeque::pop_front);
+            if let Some(item) = item {
+                self.not_full.notify_one();
+                return item;
+            }
+            notified.await;
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.lock().iter().map(VecDeque::len).sum()
+    }
+
+    /// The number of items of `priority` waiting in the queue.
+    pub(crate) fn depth(&self, priority: Priority) -> usize {
+        self.lock()[priority.index()].len()
+    }
+
+    /// The number of items discarded by the overflow policy so far.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn drop_one(&self, reason: &str) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        log::warn!("{}", reason);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, [VecDeque<T>; Priority::ALL.len()]> {
+        self.classes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use super::{IngressQueue, OverflowPolicy, Priority};
+
+    #[tokio::test]
+    async fn test_drop_newest() {
+        let queue = IngressQueue::new(2, OverflowPolicy::DropNewest);
+        queue.push(1, Priority::Normal).await;
+        queue.push(2, Priority::Normal).await;
+        queue.push(3, Priority::Normal).await;
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.dropped(), 1);
+        assert_eq!(queue.pop().await, 1);
+        assert_eq!(queue.pop().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest() {
+        let queue = IngressQueue::new(2, OverflowPolicy::DropOldest);
+        queue.push(1, Priority::Normal).await;
+        queue.push(2, Priority::Normal).await;
+        queue.push(3, Priority::Normal).await;
+        assert_eq!(queue.dropped(), 1);
+        assert_eq!(queue.pop().await, 2);
+        assert_eq!(queue.pop().await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_backpressure() {
+        let queue = IngressQueue::new(1, OverflowPolicy::Backpressure);
+        queue.push(1, Priority::Normal).await;
+        assert!(
+            timeout(Duration::from_millis(50), queue.push(2, Priority::Normal))
+                .await
+                .is_err()
+        );
+        assert_eq!(queue.pop().await, 1);
+        assert!(
+            timeout(Duration::from_millis(50), queue.push(3, Priority::Normal))
+                .await
+                .is_ok()
+        );
+        assert_eq!(queue.pop().await, 3);
+        assert_eq!(queue.dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_priority() {
+        let queue = IngressQueue::new(3, OverflowPolicy::DropOldest);
+        queue.push(1, Priority::Low).await;
+        queue.push(2, Priority::Normal).await;
+        queue.push(3, Priority::High).await;
+        assert_eq!(queue.depth(Priority::Low), 1);
+
+        // the low priority frame makes room
+        queue.push(4, Priority::High).await;
+        assert_eq!(queue.depth(Priority::Low), 0);
+        assert_eq!(queue.depth(Priority::High), 2);
+        // a low priority frame does not push out higher ones
+        queue.push(5, Priority::Low).await;
+        assert_eq!(queue.dropped(), 2);
+
+        assert_eq!(queue.pop().await, 3);
+        assert_eq!(queue.pop().await, 4);
+        assert_eq!(queue.pop().await, 2);
+        assert_eq!(queue.len(), 0);
+    }
+}
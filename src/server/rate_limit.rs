@@ -0,0 +1,160 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, PoisonError,
+    },
+    time::Instant,
+};
+
+use crate::adaptor::AdaptorId;
+
+/// A token bucket rate: `burst` frames at once, refilled at `per_second` frames per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    per_second: f64,
+    burst: f64,
+}
+
+impl RateLimit {
+    pub fn new(per_second: u32, burst: u32) -> Self {
+        Self {
+            per_second: f64::from(per_second),
+            burst: f64::from(burst.max(1)),
+        }
+    }
+}
+
+/// What the server does with a frame over the rate limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RateLimitAction {
+    /// Discard the frame silently, so a flooding node gets no traffic back.
+    #[default]
+    Drop,
+    /// Answer the frame with `ErrorStatus::RateLimited`.
+    Reject,
+}
+
+/// Identifies a bucket: a source node, and the application if it has its own limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct BucketKey {
+    pub(crate) adaptor: AdaptorId,
+    pub(crate) src_id: u8,
+    pub(crate) application: Option<u8>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    /// Add the tokens refilled since the last update.
+    fn refill(&mut self, limit: RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated);
+        self.updated = now;
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * limit.per_second).min(limit.burst);
+    }
+}
+
+/// The token buckets of every source seen so far.
+#[derive(Default)]
+pub(crate) struct RateLimiter {
+    buckets: Mutex<HashMap<BucketKey, Bucket>>,
+    limited: AtomicU64,
+}
+
+impl RateLimiter {
+    /// Take a token from the bucket of every key in `limits`, or from none of them if one is
+    /// empty. Returns false if the frame is over a limit.
+    pub(crate) fn admit(&self, limits: &[(BucketKey, RateLimit)]) -> bool {
+        self.admit_at(limits, Instant::now())
+    }
+
+    fn admit_at(&self, limits: &[(BucketKey, RateLimit)], now: Instant) -> bool {
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        for &(key, limit) in limits {
+            buckets
+                .entry(key)
+                .or_insert(Bucket {
+                    tokens: limit.burst,
+                    updated: now,
+                })
+                .refill(limit, now);
+        }
+        let admitted = limits
+            .iter()
+            .all(|(key, _)| buckets.get(key).is_some_and(|bucket| bucket.tokens >= 1.0));
+        if !admitted {
+            self.limited.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        for (key, _) in limits {
+            if let Some(bucket) = buckets.get_mut(key) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        true
+    }
+
+    /// The number of frames over the limit so far.
+    pub(crate) fn limited(&self) -> u64 {
+        self.limited.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::adaptor::AdaptorId;
+
+    use super::{BucketKey, RateLimit, RateLimiter};
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::default();
+        let limit = RateLimit::new(10, 2);
+        let key = BucketKey {
+            adaptor: AdaptorId(0),
+            src_id: 1,
+            application: None,
+        };
+        let other = BucketKey { src_id: 2, ..key };
+        let start = Instant::now();
+        assert!(limiter.admit_at(&[(key, limit)], start));
+        assert!(limiter.admit_at(&[(key, limit)], start));
+        assert!(!limiter.admit_at(&[(key, limit)], start));
+        // every source has its own bucket
+        assert!(limiter.admit_at(&[(other, limit)], start));
+        // one token is back after 100ms
+        let later = start + Duration::from_millis(100);
+        assert!(limiter.admit_at(&[(key, limit)], later));
+        assert!(!limiter.admit_at(&[(key, limit)], later));
+        assert_eq!(limiter.limited(), 2);
+    }
+    #[test]
+    fn test_all_buckets_or_none() {
+        let limiter = RateLimiter::default();
+        let global = BucketKey {
+            adaptor: AdaptorId(0),
+            src_id: 1,
+            application: None,
+        };
+        let application = BucketKey {
+            application: Some(2),
+            ..global
+        };
+        let limits = [
+            (global, RateLimit::new(0, 2)),
+            (application, RateLimit::new(0, 1)),
+        ];
+        let start = Instant::now();
+        assert!(limiter.admit_at(&limits, start));
+        // the application bucket is empty, so the global token is kept
+        assert!(!limiter.admit_at(&limits, start));
+        assert!(limiter.admit_at(&limits[..1], start));
+        assert!(!limiter.admit_at(&limits[..1], start));
+        assert_eq!(limiter.limited(), 2);
+    }
+}
@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+/// A handle to stop a running `TcspServer`.
+///
+/// After `shutdown` is called, `TcspServer::listen` stops receiving frames, waits for the in-flight
+/// application handlers until the shutdown timeout expires, flushes the adaptor and returns.
+#[derive(Clone, Debug)]
+pub struct ShutdownHandle(Arc<watch::Sender<bool>>);
+
+impl ShutdownHandle {
+    pub(crate) fn new() -> Self {
+        let (sender, _receiver) = watch::channel(false);
+        Self(Arc::new(sender))
+    }
+
+    /// Ask the server to stop. Calling it more than once has no effect.
+    pub fn shutdown(&self) {
+        self.0.send_replace(true);
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Wait until `shutdown` is called.
+    pub(crate) async fn wait(&self) {
+        let mut receiver = self.0.subscribe();
+        // The sender lives in `self`, so the channel can not be closed here.
+        let _ = receiver.wait_for(|is_shutdown| *is_shutdown).await;
+    }
+}
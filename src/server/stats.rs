@@ -0,0 +1,41 @@
+use crate::{adaptor::AdaptorStats, utils::Counter};
+
+/// A snapshot of the counters of a server, returned by `TcspServer::stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerStats {
+    /// Frames received from the adaptors.
+    pub received: u64,
+    /// Frames discarded because they were over a rate limit.
+    pub rate_limited: u64,
+    /// Frames discarded because the ingress queue was full.
+    pub dropped: u64,
+    /// Frames which could not be parsed into a request.
+    pub malformed: u64,
+    /// Requests of another protocol version.
+    pub version_mismatches: u64,
+    /// Requests of an application which is not registered.
+    pub unknown_applications: u64,
+    /// Requests refused by the access policy.
+    pub permission_denied: u64,
+    /// Requests handed to an application.
+    pub handled: u64,
+    /// Requests whose handler failed, including the timed out ones.
+    pub handler_errors: u64,
+    /// Requests whose handler exceeded its timeout.
+    pub timeouts: u64,
+    /// The counters of every adaptor, indexed by `AdaptorId`.
+    pub adaptors: Vec<AdaptorStats>,
+}
+
+/// The live counters of a server. The queue and the rate limiter keep their own.
+#[derive(Default)]
+pub(crate) struct ServerCounters {
+    pub(crate) received: Counter,
+    pub(crate) malformed: Counter,
+    pub(crate) version_mismatches: Counter,
+    pub(crate) unknown_applications: Counter,
+    pub(crate) permission_denied: Counter,
+    pub(crate) handled: Counter,
+    pub(crate) handler_errors: Counter,
+    pub(crate) timeouts: Counter,
+}
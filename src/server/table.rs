@@ -0,0 +1,94 @@
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::{
+    application::Application, protocol::v1::error_response::ERROR_RESPONSE_APPLICATION_ID,
+};
+
+use super::{ApplicationOptions, ServerError};
+
+const MAX_APPLICATION_HANDLER: usize = 256;
+
+#[derive(Clone)]
+pub(crate) struct ApplicationEntry {
+    pub(crate) application: Arc<dyn Application>,
+    pub(crate) options: ApplicationOptions,
+}
+
+impl ApplicationEntry {
+    pub(crate) fn new(application: Arc<dyn Application>, options: ApplicationOptions) -> Self {
+        Self {
+            application,
+            options,
+        }
+    }
+
+    fn id(&self) -> u8 {
+        self.application.application_id()
+    }
+}
+
+/// The applications of a server, indexed by application id.
+///
+/// A lookup clones the entry out of the table, so a request keeps its handler even if the entry is replaced meanwhile.
+pub(crate) struct ApplicationTable {
+    entries: RwLock<[Option<ApplicationEntry>; MAX_APPLICATION_HANDLER]>,
+}
+
+impl ApplicationTable {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: RwLock::new(core::array::from_fn(|_| None)),
+        }
+    }
+
+    pub(crate) fn get(&self, id: u8) -> Option<ApplicationEntry> {
+        self.entries.read().unwrap_or_else(PoisonError::into_inner)[id as usize].clone()
+    }
+
+    /// Every registered application, ordered by application id.
+    pub(crate) fn entries(&self) -> Vec<ApplicationEntry> {
+        self.entries
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    /// Fails if `entry` uses a reserved id, or if `allow_replace` is false and the id is taken.
+    pub(crate) fn check(
+        &self,
+        entry: &ApplicationEntry,
+        allow_replace: bool,
+    ) -> Result<(), ServerError> {
+        let id = entry.id();
+        if id == ERROR_RESPONSE_APPLICATION_ID {
+            return Err(ServerError::ReservedApplication {
+                id,
+                name: entry.application.application_name(),
+            });
+        }
+        if allow_replace {
+            return Ok(());
+        }
+        match self.get(id) {
+            Some(previous) => Err(ServerError::DuplicateApplication {
+                id,
+                first: previous.application.application_name(),
+                second: entry.application.application_name(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Put `entry` into the table, and return the entry it replaces.
+    pub(crate) fn insert(&self, entry: ApplicationEntry) -> Option<ApplicationEntry> {
+        let id = entry.id();
+        self.entries.write().unwrap_or_else(PoisonError::into_inner)[id as usize].replace(entry)
+    }
+
+    pub(crate) fn remove(&self, id: u8) -> Option<ApplicationEntry> {
+        self.entries.write().unwrap_or_else(PoisonError::into_inner)[id as usize].take()
+    }
+}
@@ -1 +1,2 @@
+mod test_client;
 mod test_server;
\ No newline at end of file
@@ -0,0 +1,392 @@
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use tokio::sync::mpsc::channel;
+
+use crate::{
+    adaptor::{Channel, DeviceAdaptor, DeviceAdaptorError, Frame as BusFrame, FrameFlag, Uart},
+    application::{
+        Application, Diagnostics, DummyFallback, EchoCommand, Reboot, ResetNetwork, Response,
+        TeleMetry, TimeSync,
+    },
+    client::{
+        application_name, find_application, ArgumentValue, ClientError, Decoded, RetryPolicy,
+        TcspClient, TcspClientBuilder, REGISTRY,
+    },
+    protocol::{ErrorStatus, Frame},
+    server::{ApplicationOptions, Backoff, TcspServerBuilder},
+};
+
+const SERVER_ID: u8 = 1;
+
+#[tokio::test]
+async fn test_client_request() {
+    let (to_server, from_client) = channel(32);
+    let (to_client, from_server) = channel(32);
+    let unsolicited_sender = to_client.clone();
+    let server = TcspServerBuilder::new(Channel::new(to_client, from_client))
+        .with_application(Arc::new(EchoCommand {}))
+        .build()
+        .unwrap();
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+    let (unsolicited_tx, mut unsolicited_rx) = channel(4);
+    let client = Arc::new(
+        TcspClientBuilder::new(Channel::new(to_server, from_server))
+            .with_unsolicited(unsolicited_tx)
+            .build(),
+    );
+
+    let request = Frame::new_from_slice(EchoCommand::APPLICATION_ID, &[1, 2, 3]).unwrap();
+    let response = client.request(SERVER_ID, request).await.unwrap();
+    assert_eq!(response.application(), EchoCommand::APPLICATION_ID);
+    assert_eq!(response.meta().src_id(), SERVER_ID);
+    assert_eq!(&response.data()[..3], [1, 2, 3]);
+
+    // concurrent requests get their own responses
+    let requests = (0..8u8).map(|i| {
+        let client = Arc::clone(&client);
+        async move {
+            let request = Frame::new_from_slice(EchoCommand::APPLICATION_ID, &[i]).unwrap();
+            client.request(SERVER_ID, request).await.unwrap().data()[0]
+        }
+    });
+    assert_eq!(join_all(requests).await, (0..8).collect::<Vec<_>>());
+
+    let request = Frame::new_from_slice(100, &[]).unwrap();
+    let Err(ClientError::Rejected(error)) = client.request(SERVER_ID, request).await else {
+        panic!("a request to an unknown application should be rejected");
+    };
+    assert_eq!(error.status, ErrorStatus::UnknownApplication);
+    assert_eq!(error.application, 100);
+
+    // a frame nobody waits for
+    let frame = Frame::new_from_slice(0, &[9]).unwrap();
+    unsolicited_sender
+        .send(frame.try_into().unwrap())
+        .await
+        .unwrap();
+    let frame = unsolicited_rx.recv().await.unwrap();
+    assert_eq!(frame.application(), 0);
+    assert_eq!(frame.data(), [9]);
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_client_timeout() {
+    let (to_server, _from_client) = channel(32);
+    let (_to_client, from_server) = channel(32);
+    let client: TcspClient<Channel> = TcspClientBuilder::new(Channel::new(to_server, from_server))
+        .with_timeout(Duration::from_millis(20))
+        .build();
+    let request = Frame::new_from_slice(EchoCommand::APPLICATION_ID, &[1]).unwrap();
+    let result = client.request(SERVER_ID, request).await;
+    assert!(matches!(result, Err(ClientError::Timeout(_))));
+}
+
+#[tokio::test]
+async fn test_client_stubs() {
+    let (to_server, from_client) = channel(32);
+    let (to_client, from_server) = channel(32);
+    let server = TcspServerBuilder::new(Channel::new(to_client, from_client))
+        .with_application(Arc::new(EchoCommand {}))
+        .with_application(Arc::new(Reboot {}))
+        .with_application(Arc::new(ResetNetwork {}))
+        .with_application(Arc::new(TeleMetry::new(DummyFallback {})))
+        .with_application(Arc::new(TimeSync::new(DummyFallback {})))
+        .with_application(Arc::new(Diagnostics::new()))
+        .build()
+        .unwrap();
+    tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+    let client = TcspClientBuilder::new(Channel::new(to_server, from_server)).build();
+
+    assert_eq!(client.echo(SERVER_ID, b"hello").await.unwrap(), b"hello");
+    client.reboot(SERVER_ID).await.unwrap();
+    // the dummy fallback answers the telemetry code
+    let telemetry = client.telemetry(SERVER_ID).await.unwrap();
+    assert_eq!(telemetry.len(), 100);
+    assert_eq!(telemetry[..4], [0, 0, 0xea, 0x60]);
+    // without the interfaces, their status is empty
+    client.list_network(SERVER_ID).await.unwrap();
+    client
+        .sync_time(SERVER_ID, chrono::Utc::now())
+        .await
+        .unwrap();
+
+    let datetime = chrono::DateTime::from_timestamp(i64::from(u32::MAX) + 1, 0).unwrap();
+    assert!(matches!(
+        client.sync_time(SERVER_ID, datetime).await,
+        Err(ClientError::Frame(_))
+    ));
+
+    // every request so far reached the server, this one included
+    let stats = client.diagnostics(SERVER_ID).await.unwrap();
+    assert_eq!(stats.received, 6);
+    assert_eq!(stats.handled, 6);
+    assert_eq!(stats.adaptors.len(), 1);
+    assert_eq!(stats.adaptors[0].received, 6);
+}
+
+#[tokio::test]
+async fn test_client_registry() {
+    let (to_server, from_client) = channel(32);
+    let (to_client, from_server) = channel(32);
+    let server = TcspServerBuilder::new(Channel::new(to_client, from_client))
+        .with_application(Arc::new(EchoCommand {}))
+        .with_application(Arc::new(Reboot {}))
+        .with_application(Arc::new(Diagnostics::new()))
+        .build()
+        .unwrap();
+    tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+    let client = TcspClientBuilder::new(Channel::new(to_server, from_server)).build();
+
+    for application in REGISTRY {
+        assert_eq!(find_application(application.name).unwrap().id, application.id);
+        assert_eq!(application_name(application.id), Some(application.name));
+    }
+    let echo = &find_application("echo").unwrap().operations[0];
+    let arguments = [ArgumentValue::Bytes(b"hi".to_vec())];
+    let decoded = client.invoke(SERVER_ID, echo, &arguments).await.unwrap();
+    assert_eq!(decoded, Decoded::Bytes(b"hi".to_vec()));
+    // the arguments do not match the operation
+    assert!(matches!(
+        client.invoke(SERVER_ID, echo, &[]).await,
+        Err(ClientError::Frame(_))
+    ));
+
+    let reboot = &find_application("reboot").unwrap().operations[0];
+    let Decoded::Record(fields) = client.invoke(SERVER_ID, reboot, &[]).await.unwrap() else {
+        panic!("a reboot is acknowledged with its status");
+    };
+    assert_eq!(fields, [("status", Decoded::Text("rebooting".to_owned()))]);
+
+    let diagnostics = &find_application("diagnostics").unwrap().operations[0];
+    let Decoded::Record(fields) = client.invoke(SERVER_ID, diagnostics, &[]).await.unwrap() else {
+        panic!("the counters are decoded as a record");
+    };
+    // the echo and the reboot before
+    assert_eq!(fields[0], ("received", Decoded::Number(3)));
+    assert_eq!(
+        fields.last().unwrap().1,
+        Decoded::List(vec![Decoded::Record(vec![
+            ("received", Decoded::Number(3)),
+            ("sent", Decoded::Number(2)),
+            ("send_errors", Decoded::Number(0)),
+            ("parse_errors", Decoded::Number(0)),
+            ("checksum_errors", Decoded::Number(0)),
+            ("slot_overflows", Decoded::Number(0)),
+        ])])
+    );
+}
+
+#[tokio::test]
+async fn test_client_over_uart() {
+    let (node_port, client_port) = serialport::TTYPort::pair().unwrap();
+    let server = TcspServerBuilder::new(Uart::from_port(Box::new(node_port)))
+        .with_application(Arc::new(EchoCommand {}))
+        .build()
+        .unwrap();
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+    let client = TcspClientBuilder::new(Uart::from_port(Box::new(client_port))).build();
+
+    // the uart does not carry node ids, the node at the other end answers whatever its id
+    assert_eq!(client.echo(5, b"hello").await.unwrap(), b"hello");
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+}
+
+/// Answers every request with how many requests it has handled.
+struct Counter(AtomicU32);
+
+#[async_trait]
+impl Application for Counter {
+    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
+        let count = self.0.fetch_add(1, Ordering::AcqRel) + 1;
+        let mut response = Frame::new(13);
+        response.set_meta_from_request(frame.meta());
+        response.set_len(1)?;
+        response.data_mut()[0] = count as u8;
+        Ok(Response::Single(response))
+    }
+
+    fn application_id(&self) -> u8 {
+        13
+    }
+
+    fn application_name(&self) -> &'static str {
+        "Counter"
+    }
+}
+
+/// An adaptor losing the first `lost` frames it receives.
+struct Lossy {
+    channel: Channel,
+    lost: AtomicU32,
+}
+
+#[async_trait]
+impl DeviceAdaptor for Lossy {
+    async fn send(&self, frame: BusFrame) -> Result<(), DeviceAdaptorError> {
+        self.channel.send(frame).await
+    }
+
+    async fn recv(&self) -> Result<BusFrame, DeviceAdaptorError> {
+        loop {
+            let frame = self.channel.recv().await?;
+            let lost = self
+                .lost
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1));
+            if lost.is_err() {
+                return Ok(frame);
+            }
+        }
+    }
+
+    fn mtu(&self, flag: FrameFlag) -> usize {
+        self.channel.mtu(flag)
+    }
+}
+
+#[tokio::test]
+async fn test_client_retry() {
+    let (to_server, from_client) = channel(32);
+    let (to_client, from_server) = channel(32);
+    let server = TcspServerBuilder::new(Channel::new(to_client, from_client))
+        .with_application_options(
+            Arc::new(Counter(AtomicU32::new(0))),
+            ApplicationOptions::new().dedup(Duration::from_secs(1)),
+        )
+        .build()
+        .unwrap();
+    tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+    let adaptor = Lossy {
+        channel: Channel::new(to_server, from_server),
+        lost: AtomicU32::new(2),
+    };
+    let client = TcspClientBuilder::new(adaptor)
+        .with_timeout(Duration::from_millis(50))
+        .build();
+    let policy = RetryPolicy::new(4)
+        .with_backoff(Backoff::new(Duration::from_millis(1), Duration::from_millis(10)));
+
+    let request = || Frame::new_from_slice(13, &[]).unwrap();
+
+    // the first two responses are lost, the third one is replayed by the server
+    let reply = client
+        .request_with_retry(SERVER_ID, request(), &policy)
+        .await
+        .unwrap();
+    assert_eq!(reply.attempts, 3);
+    assert_eq!(reply.frame.data(), [1]);
+
+    let reply = client
+        .request_with_retry(SERVER_ID, request(), &policy)
+        .await
+        .unwrap();
+    assert_eq!(reply.attempts, 1);
+    assert_eq!(reply.frame.data(), [2]);
+
+    // every response is lost
+    client.adaptor().lost.store(u32::MAX, Ordering::Release);
+    let result = client
+        .request_with_retry(SERVER_ID, request(), &policy)
+        .await;
+    assert!(matches!(
+        result,
+        Err(ClientError::Unanswered { attempts: 4, .. })
+    ));
+    let policy = policy.with_deadline(Duration::from_millis(80));
+    let result = client
+        .request_with_retry(SERVER_ID, request(), &policy)
+        .await;
+    // the deadline leaves no time for the last attempts
+    assert!(matches!(
+        result,
+        Err(ClientError::Unanswered { attempts, .. }) if attempts < 4
+    ));
+}
+
+/// Answers every request with as many frames as its first byte, numbered from 0.
+struct Split;
+
+#[async_trait]
+impl Application for Split {
+    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
+        let frames = (0..frame.data()[0])
+            .map(|index| {
+                let mut response = Frame::new(15);
+                response.set_meta_from_request(frame.meta());
+                response.set_len(1)?;
+                response.data_mut()[0] = index;
+                Ok(response)
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok(Response::Frames(frames))
+    }
+
+    fn application_id(&self) -> u8 {
+        15
+    }
+
+    fn application_name(&self) -> &'static str {
+        "Split"
+    }
+}
+
+#[tokio::test]
+async fn test_client_request_frames() {
+    let (to_server, from_client) = channel(32);
+    let (to_client, from_server) = channel(32);
+    let server = TcspServerBuilder::new(Channel::new(to_client, from_client))
+        .with_application_options(
+            Arc::new(Split),
+            ApplicationOptions::new().pacing(Duration::from_millis(10)),
+        )
+        .build()
+        .unwrap();
+    tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+    let (unsolicited_tx, mut unsolicited_rx) = channel(4);
+    let client = TcspClientBuilder::new(Channel::new(to_server, from_server))
+        .with_timeout(Duration::from_millis(200))
+        .with_unsolicited(unsolicited_tx)
+        .build();
+    let idle = Duration::from_millis(50);
+
+    let request = Frame::new_from_slice(15, &[3]).unwrap();
+    let frames = client.request_frames(SERVER_ID, request, idle).await.unwrap();
+    let indexes: Vec<u8> = frames.iter().map(|frame| frame.data()[0]).collect();
+    assert_eq!(indexes, [0, 1, 2]);
+    assert!(unsolicited_rx.try_recv().is_err());
+
+    // an empty response is never completed
+    let request = Frame::new_from_slice(15, &[0]).unwrap();
+    let result = client.request_frames(SERVER_ID, request, idle).await;
+    assert!(matches!(result, Err(ClientError::Timeout(_))));
+
+    let request = Frame::new_from_slice(100, &[]).unwrap();
+    let result = client.request_frames(SERVER_ID, request, idle).await;
+    assert!(matches!(result, Err(ClientError::Rejected(_))));
+}
@@ -1,16 +1,33 @@
-use std::sync::Arc;
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use async_trait::async_trait;
+use futures_util::StreamExt;
 use tokio::{
     self,
     sync::{mpsc::channel, Mutex},
+    time::timeout,
 };
 
 use crate::{
-    adaptor::{send_using_ty_protocol, Channel},
-    application::{Application, DummyFallback, EchoCommand, TeleMetry, TimeSync},
-    protocol::v1::frame::Frame,
-    server::TcspServer,
+    adaptor::{
+        send_using_ty_protocol, AdaptorId, Channel, DeviceAdaptor, DeviceAdaptorError,
+        Frame as BusFrame, FrameFlag, FrameMeta,
+    },
+    application::{
+        Application, Diagnostics, DummyFallback, EchoCommand, Fallback, Health, ResetNetwork,
+        Response, TeleMetry, TimeSync,
+    },
+    protocol::{v1::frame::Frame, ErrorResponse, ErrorStatus},
+    server::{
+        AccessPolicy, ApplicationOptions, ApplicationTiming, Backoff, Downlink, Middleware, Next, Priority,
+        RateLimit, RateLimitAction, RequestLogger, ServerError, TcspServerBuilder,
+    },
     UdpBackup,
 };
 
@@ -23,10 +40,15 @@ async fn test_server_channel() {
     let tel: Arc<dyn Application> = Arc::new(TeleMetry::new(socket.clone()));
     let echo: Arc<dyn Application> = Arc::new(EchoCommand {});
     let time: Arc<dyn Application> = Arc::new(TimeSync::new(socket));
-    let applications = [tel, echo, time].into_iter();
-    let server = TcspServer::new_channel(adaptor, applications);
-    tokio::spawn(async move {
-        server.listen().await;
+    let server = TcspServerBuilder::new(adaptor)
+        .with_application(tel)
+        .with_application(echo)
+        .with_application(time)
+        .build()
+        .unwrap();
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn(async move {
+        server.listen().await.unwrap();
     });
 
     // suppose we receive a telemetry request
@@ -42,8 +64,7 @@ async fn test_server_channel() {
 
     // suppose we receive a echo request
     let content = (1..=42).collect::<Vec<u8>>();
-    let echo = EchoCommand {};
-    let echo_req = echo.request(150, &content).unwrap();
+    let echo_req = EchoCommand::request(&content).unwrap();
     rx_sender.send(echo_req.try_into().unwrap()).await.unwrap();
     // we expect to receive a response same as request
     let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
@@ -54,14 +75,20 @@ async fn test_server_channel() {
     // suppose we recevie a time broadcast request
     let time_req = TimeSync::<()>::request_now().unwrap();
     rx_sender.send(time_req.try_into().unwrap()).await.unwrap();
+
+    shutdown.shutdown();
+    timeout(Duration::from_secs(1), listening)
+        .await
+        .unwrap()
+        .unwrap();
 }
 
 struct Foo;
 
 #[async_trait]
 impl Application for Foo {
-    async fn handle(&self, _frame: Frame, _mtu: u16) -> std::io::Result<Option<Frame>> {
-        Ok(None)
+    async fn handle(&self, _frame: Frame, _mtu: u16) -> std::io::Result<Response> {
+        Ok(Response::Empty)
     }
 
     fn application_id(&self) -> u8 {
@@ -74,7 +101,6 @@ impl Application for Foo {
 }
 
 #[tokio::test]
-#[should_panic(expected = "Duplicate application id of Telemetry and Foo, with same id 0")]
 async fn test_duplicate_id() {
     let (tx_sender, _tx_receiver) = channel(32);
     let (_rx_sender, rx_receiver) = channel(32);
@@ -82,8 +108,1298 @@ async fn test_duplicate_id() {
     let socket = DummyFallback {};
     let tel: Arc<dyn Application> = Arc::new(TeleMetry::new(socket.clone()));
     let foo: Arc<dyn Application> = Arc::new(Foo {});
-    let applications = [tel, foo].into_iter();
-    let _server = TcspServer::new_channel(adaptor, applications);
+    let result = TcspServerBuilder::new(adaptor)
+        .with_application(tel)
+        .with_application(foo)
+        .build();
+    let Err(e) = result else {
+        panic!("duplicate application id should be rejected");
+    };
+    assert!(matches!(e, ServerError::DuplicateApplication { id: 0, .. }));
+    assert_eq!(
+        e.to_string(),
+        "Duplicate application id of Telemetry and Foo, with same id 0"
+    );
+}
+
+#[tokio::test]
+async fn test_dyn_adaptor() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let adaptor: Box<dyn DeviceAdaptor> = Box::new(Channel::new(tx_sender, rx_receiver));
+    let server = TcspServerBuilder::new(adaptor)
+        .with_application(Arc::new(EchoCommand {}))
+        .build()
+        .unwrap();
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    let echo_req = EchoCommand::request(&[1, 2, 3]).unwrap();
+    rx_sender.send(echo_req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(resp.data(), &[1, 2, 3]);
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+}
+
+/// Sleeps `data[0] * 10` ms, then echoes the request.
+struct Sleepy(u8);
+
+#[async_trait]
+impl Application for Sleepy {
+    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
+        let delay = u64::from(frame.data()[0]) * 10;
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+        let mut response = Frame::new_from_slice(self.0, frame.data())?;
+        response.set_meta_from_request(frame.meta());
+        Ok(Response::Single(response))
+    }
+
+    fn application_id(&self) -> u8 {
+        self.0
+    }
+
+    fn application_name(&self) -> &'static str {
+        "Sleepy"
+    }
+}
+
+#[tokio::test]
+async fn test_slow_application_does_not_block() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let adaptor = Channel::new(tx_sender, rx_receiver);
+    let server = TcspServerBuilder::new(adaptor)
+        .with_application(Arc::new(Sleepy(10)))
+        .with_application(Arc::new(EchoCommand {}))
+        .build()
+        .unwrap();
+    tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    let slow_req = Frame::new_from_slice(10, &[50]).unwrap();
+    rx_sender.send(slow_req.try_into().unwrap()).await.unwrap();
+    let echo_req = EchoCommand::request(&[1, 2, 3]).unwrap();
+    rx_sender.send(echo_req.try_into().unwrap()).await.unwrap();
+
+    let first: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(first.application(), EchoCommand::APPLICATION_ID);
+    let second: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(second.application(), 10);
+}
+
+#[tokio::test]
+async fn test_ordered_application() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let adaptor = Channel::new(tx_sender, rx_receiver);
+    let server = TcspServerBuilder::new(adaptor)
+        .with_application_options(Arc::new(Sleepy(10)), ApplicationOptions::new().ordered(true))
+        .build()
+        .unwrap();
+    tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    // later requests finish faster, but the responses must keep the request order
+    for delay in (1..=5).rev() {
+        let req = Frame::new_from_slice(10, &[delay]).unwrap();
+        rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    }
+    for delay in (1..=5).rev() {
+        let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+        assert_eq!(resp.data()[0], delay);
+    }
+}
+
+#[tokio::test]
+async fn test_ordered_lane_full() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let adaptor = Channel::new(tx_sender, rx_receiver);
+    let server = TcspServerBuilder::new(adaptor)
+        .with_application_options(Arc::new(Sleepy(10)), ApplicationOptions::new().ordered(true))
+        .with_application(Arc::new(Sleepy(11)))
+        .with_queue_capacity(1)
+        .build()
+        .unwrap();
+    let server = Arc::new(server);
+    tokio::spawn({
+        let server = Arc::clone(&server);
+        async move { server.listen().await.unwrap() }
+    });
+
+    let req = Frame::new_from_slice(10, &[20]).unwrap();
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    // the first request is handled and the second one fills the lane
+    for application in [10, 10, 11] {
+        let req = Frame::new_from_slice(application, &[0]).unwrap();
+        rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    }
+
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    let error = ErrorResponse::try_from(&resp).unwrap();
+    assert_eq!(error.status, ErrorStatus::Busy);
+    assert_eq!(error.application, 10);
+    // the other applications are not held up by the full lane
+    let resp: Frame = timeout(Duration::from_millis(100), tx_receiver.recv())
+        .await
+        .unwrap()
+        .unwrap()
+        .try_into()
+        .unwrap();
+    assert_eq!(resp.application(), 11);
+    for delay in [20, 0] {
+        let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+        assert_eq!((resp.application(), resp.data()[0]), (10, delay));
+    }
+    assert_eq!(server.dropped_frames(), 1);
+}
+
+#[tokio::test]
+async fn test_shutdown_drains_in_flight() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let adaptor = Channel::new(tx_sender, rx_receiver);
+    let server = TcspServerBuilder::new(adaptor)
+        .with_application(Arc::new(Sleepy(10)))
+        .build()
+        .unwrap();
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    let req = Frame::new_from_slice(10, &[20]).unwrap();
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    shutdown.shutdown();
+    assert!(shutdown.is_shutdown());
+
+    // the in-flight request still gets its response before `listen` returns
+    timeout(Duration::from_secs(1), listening)
+        .await
+        .unwrap()
+        .unwrap();
+    let resp: Frame = tx_receiver.try_recv().unwrap().try_into().unwrap();
+    assert_eq!(resp.data()[0], 20);
+}
+
+#[tokio::test]
+async fn test_shutdown_timeout() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let adaptor = Channel::new(tx_sender, rx_receiver);
+    let server = TcspServerBuilder::new(adaptor)
+        .with_application(Arc::new(Sleepy(10)))
+        .with_shutdown_timeout(Duration::from_millis(50))
+        .build()
+        .unwrap();
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    let req = Frame::new_from_slice(10, &[255]).unwrap();
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    shutdown.shutdown();
+
+    // the handler is aborted instead of being waited for
+    timeout(Duration::from_millis(500), listening)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(tx_receiver.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_shutdown_waiting_for_permit() {
+    let (tx_sender, _tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let adaptor = Channel::new(tx_sender, rx_receiver);
+    let server = TcspServerBuilder::new(adaptor)
+        .with_application(Arc::new(Sleepy(10)))
+        .with_max_in_flight(1)
+        .with_shutdown_timeout(Duration::from_millis(50))
+        .build()
+        .unwrap();
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    // the first request holds the only permit, the second one waits for it
+    for _ in 0..2 {
+        let req = Frame::new_from_slice(10, &[255]).unwrap();
+        rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    shutdown.shutdown();
+
+    timeout(Duration::from_millis(500), listening)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+/// Responds with the index of the adaptor the request came from.
+struct WhoAmI;
+
+#[async_trait]
+impl Application for WhoAmI {
+    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
+        let mut response = Frame::new(11);
+        response.set_meta_from_request(frame.meta());
+        response.set_len(1)?;
+        response.data_mut()[0] = frame.adaptor().index() as u8;
+        Ok(Response::Single(response))
+    }
+
+    fn application_id(&self) -> u8 {
+        11
+    }
+
+    fn application_name(&self) -> &'static str {
+        "WhoAmI"
+    }
+}
+
+#[tokio::test]
+async fn test_multiple_adaptors() {
+    let (can_tx_sender, mut can_tx_receiver) = channel(32);
+    let (can_rx_sender, can_rx_receiver) = channel(32);
+    let (uart_tx_sender, mut uart_tx_receiver) = channel(32);
+    let (uart_rx_sender, uart_rx_receiver) = channel(32);
+    let server = TcspServerBuilder::new(Channel::new(can_tx_sender, can_rx_receiver))
+        .with_adaptor(Channel::new(uart_tx_sender, uart_rx_receiver))
+        .with_application(Arc::new(WhoAmI))
+        .build()
+        .unwrap();
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    let req = Frame::new_from_slice(11, &[]).unwrap();
+    uart_rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = uart_tx_receiver.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(resp.data(), &[1]);
+
+    let req = Frame::new_from_slice(11, &[]).unwrap();
+    can_rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = can_tx_receiver.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(resp.data(), &[0]);
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+    // every response left through the adaptor of its request
+    assert!(can_tx_receiver.try_recv().is_err());
+    assert!(uart_tx_receiver.try_recv().is_err());
+}
+
+/// Records its lifecycle calls into `events`.
+struct Lifecycle {
+    id: u8,
+    fail_init: bool,
+    events: Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl Application for Lifecycle {
+    async fn handle(&self, _frame: Frame, _mtu: u16) -> std::io::Result<Response> {
+        Ok(Response::Empty)
+    }
+
+    fn application_id(&self) -> u8 {
+        self.id
+    }
+
+    fn application_name(&self) -> &'static str {
+        "Lifecycle"
+    }
+
+    async fn init(&self, _downlink: Downlink) -> std::io::Result<()> {
+        self.events.lock().unwrap().push(format!("init {}", self.id));
+        if self.fail_init {
+            return Err(std::io::Error::other("init failed"));
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> std::io::Result<()> {
+        self.events.lock().unwrap().push(format!("shutdown {}", self.id));
+        Ok(())
+    }
+
+    async fn health(&self) -> Health {
+        Health::Degraded(format!("degraded {}", self.id))
+    }
+}
+
+#[tokio::test]
+async fn test_application_lifecycle() {
+    let (tx_sender, _tx_receiver) = channel(32);
+    let (_rx_sender, rx_receiver) = channel(32);
+    let adaptor = Channel::new(tx_sender, rx_receiver);
+    let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let server = TcspServerBuilder::new(adaptor)
+        .with_application(Arc::new(EchoCommand {}))
+        .with_application(Arc::new(Lifecycle {
+            id: 10,
+            fail_init: false,
+            events: Arc::clone(&events),
+        }))
+        .build()
+        .unwrap();
+
+    let report = server.health().await;
+    assert_eq!(report.applications.len(), 2);
+    assert_eq!(report.overall(), Health::Degraded("degraded 10".to_owned()));
+
+    let shutdown = server.shutdown_handle();
+    shutdown.shutdown();
+    server.listen().await.unwrap();
+    assert_eq!(*events.lock().unwrap(), ["init 10", "shutdown 10"]);
+}
+
+#[tokio::test]
+async fn test_application_init_failed() {
+    let (tx_sender, _tx_receiver) = channel(32);
+    let (_rx_sender, rx_receiver) = channel(32);
+    let adaptor = Channel::new(tx_sender, rx_receiver);
+    let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let server = TcspServerBuilder::new(adaptor)
+        .with_application(Arc::new(Lifecycle {
+            id: 10,
+            fail_init: false,
+            events: Arc::clone(&events),
+        }))
+        .with_application(Arc::new(Lifecycle {
+            id: 20,
+            fail_init: true,
+            events: Arc::clone(&events),
+        }))
+        .build()
+        .unwrap();
+
+    let result = timeout(Duration::from_secs(1), server.listen()).await.unwrap();
+    assert!(matches!(result, Err(ServerError::Init { id: 20, .. })));
+    // the applications initialized before the failure are shut down again
+    assert_eq!(*events.lock().unwrap(), ["init 10", "init 20", "shutdown 10"]);
+}
+
+/// Always fails.
+struct Failing;
+
+#[async_trait]
+impl Application for Failing {
+    async fn handle(&self, _frame: Frame, _mtu: u16) -> std::io::Result<Response> {
+        Err(std::io::Error::other("boom"))
+    }
+
+    fn application_id(&self) -> u8 {
+        12
+    }
+
+    fn application_name(&self) -> &'static str {
+        "Failing"
+    }
+}
+
+#[tokio::test]
+async fn test_error_response() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let server = TcspServerBuilder::new(Channel::new(tx_sender, rx_receiver))
+        .with_application(Arc::new(Failing))
+        .build()
+        .unwrap();
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    // unknown application
+    let req = Frame::new_from_slice(42, &[]).unwrap();
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    let error = ErrorResponse::try_from(&resp).unwrap();
+    assert_eq!(error.status, ErrorStatus::UnknownApplication);
+    assert_eq!(error.application, 42);
+
+    // unsupported version
+    let req = Frame::new_from_slice(12, &[]).unwrap();
+    let mut bus_frame: BusFrame = req.try_into().unwrap();
+    bus_frame.data_mut()[0] = 0x7f;
+    rx_sender.send(bus_frame).await.unwrap();
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    let error = ErrorResponse::try_from(&resp).unwrap();
+    assert_eq!(error.status, ErrorStatus::VersionMismatch);
+    assert_eq!(error.application, 12);
+
+    // handler failure
+    let req = Frame::new_from_slice(12, &[]).unwrap();
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    let error = ErrorResponse::try_from(&resp).unwrap();
+    assert_eq!(error.status, ErrorStatus::HandlerError);
+    assert_eq!(error.application, 12);
+    assert_eq!(error.reason, "boom");
+
+    // a broadcast request is not answered
+    let mut req = Frame::new_from_slice(12, &[]).unwrap();
+    req.meta_mut().dest_id = 0xfd;
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    assert!(timeout(Duration::from_millis(100), tx_receiver.recv())
+        .await
+        .is_err());
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_short_request() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let server = TcspServerBuilder::new(Channel::new(tx_sender, rx_receiver))
+        .with_application(Arc::new(TimeSync::new(DummyFallback {})))
+        .with_application(Arc::new(ResetNetwork {}))
+        .build()
+        .unwrap();
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    // too short to hold a timestamp or a network command, the server answers instead of panicking
+    let requests = [
+        (TimeSync::<()>::APPLICATION_ID, &[0, 1][..]),
+        (ResetNetwork::APPLICATION_ID, &[]),
+    ];
+    for (application, data) in requests {
+        let req = Frame::new_from_slice(application, data).unwrap();
+        rx_sender.send(req.try_into().unwrap()).await.unwrap();
+        let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+        let error = ErrorResponse::try_from(&resp).unwrap();
+        assert_eq!(error.status, ErrorStatus::HandlerError);
+        assert_eq!(error.application, application);
+    }
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_application_timeout() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let server = TcspServerBuilder::new(Channel::new(tx_sender, rx_receiver))
+        .with_application_options(
+            Arc::new(Sleepy(1)),
+            ApplicationOptions::new().timeout(Duration::from_millis(50)),
+        )
+        .build()
+        .unwrap();
+    let server = Arc::new(server);
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn({
+        let server = Arc::clone(&server);
+        async move { server.listen().await.unwrap() }
+    });
+
+    // handled within the deadline
+    let req = Frame::new_from_slice(1, &[1]).unwrap();
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(resp.application(), 1);
+
+    // sleeps 200ms, longer than the deadline
+    let req = Frame::new_from_slice(1, &[20]).unwrap();
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = timeout(Duration::from_millis(150), tx_receiver.recv())
+        .await
+        .unwrap()
+        .unwrap()
+        .try_into()
+        .unwrap();
+    let error = ErrorResponse::try_from(&resp).unwrap();
+    assert_eq!(error.status, ErrorStatus::Timeout);
+    assert_eq!(error.application, 1);
+    assert_eq!(server.timed_out_requests(), 1);
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+}
+
+/// A fallback which never replies.
+struct Unresponsive;
+
+#[async_trait]
+impl Fallback for Unresponsive {
+    async fn fallback(&self, _msg: Vec<u8>) -> std::io::Result<Vec<u8>> {
+        std::future::pending().await
+    }
+}
+
+#[tokio::test]
+async fn test_fallback_default_timeout() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let server = TcspServerBuilder::new(Channel::new(tx_sender, rx_receiver))
+        .with_application(Arc::new(TeleMetry::new(Unresponsive)))
+        .build()
+        .unwrap();
+    let server = Arc::new(server);
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn({
+        let server = Arc::clone(&server);
+        async move { server.listen().await.unwrap() }
+    });
+
+    // registered without a timeout, the telemetry gives up on its fallback after 100ms
+    let req = Frame::new(0);
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = timeout(Duration::from_millis(500), tx_receiver.recv())
+        .await
+        .unwrap()
+        .unwrap()
+        .try_into()
+        .unwrap();
+    let error = ErrorResponse::try_from(&resp).unwrap();
+    assert_eq!(error.status, ErrorStatus::Timeout);
+    assert_eq!(server.timed_out_requests(), 1);
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+}
+
+/// Streams a first frame at once and a second one after 200ms.
+struct Trickle;
+
+#[async_trait]
+impl Application for Trickle {
+    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
+        let meta = *frame.meta();
+        let frames = futures_util::stream::iter(0..2u8).then(move |index| async move {
+            if index > 0 {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+            let mut response = Frame::new(15);
+            response.set_meta_from_request(&meta);
+            response.set_len(1)?;
+            response.data_mut()[0] = index;
+            Ok(response)
+        });
+        Ok(Response::stream(frames))
+    }
+
+    fn application_id(&self) -> u8 {
+        15
+    }
+
+    fn application_name(&self) -> &'static str {
+        "Trickle"
+    }
+}
+
+#[tokio::test]
+async fn test_stream_timeout() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let server = TcspServerBuilder::new(Channel::new(tx_sender, rx_receiver))
+        .with_application_options(
+            Arc::new(Trickle),
+            ApplicationOptions::new().timeout(Duration::from_millis(50)),
+        )
+        .build()
+        .unwrap();
+    let server = Arc::new(server);
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn({
+        let server = Arc::clone(&server);
+        async move { server.listen().await.unwrap() }
+    });
+
+    let req = Frame::new(15);
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(resp.data(), &[0]);
+    // the second frame comes after the deadline, and is not sent
+    assert!(timeout(Duration::from_millis(300), tx_receiver.recv())
+        .await
+        .is_err());
+    assert_eq!(server.timed_out_requests(), 1);
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+}
+
+/// Rejects every request of application `0`.
+struct DenyTelemetry;
+
+#[async_trait]
+impl Middleware for DenyTelemetry {
+    async fn handle(&self, frame: Frame, mtu: u16, next: Next<'_>) -> std::io::Result<Response> {
+        if frame.application() == 0 {
+            return Err(ErrorResponse::new(ErrorStatus::HandlerError, 0, "denied").into());
+        }
+        next.run(frame, mtu).await
+    }
+}
+
+#[tokio::test]
+async fn test_middleware() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let timing = Arc::new(ApplicationTiming::new());
+    let server = TcspServerBuilder::new(Channel::new(tx_sender, rx_receiver))
+        .with_middleware(Arc::new(RequestLogger))
+        .with_middleware(Arc::clone(&timing) as Arc<dyn Middleware>)
+        .with_middleware(Arc::new(DenyTelemetry))
+        .with_application(Arc::new(Foo))
+        .with_application(Arc::new(Sleepy(1)))
+        .build()
+        .unwrap();
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    let req = Frame::new_from_slice(1, &[2]).unwrap();
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(resp.application(), 1);
+
+    let req = Frame::new_from_slice(0, &[]).unwrap();
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    let error = ErrorResponse::try_from(&resp).unwrap();
+    assert_eq!(error.status, ErrorStatus::HandlerError);
+    assert_eq!(error.reason, "denied");
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+    let sleepy = timing.get(1).unwrap();
+    assert_eq!(sleepy.requests, 1);
+    assert!(sleepy.max >= Duration::from_millis(20));
+    // the timing layer wraps the rejecting one
+    assert_eq!(timing.get(0).unwrap().failures, 1);
+}
+
+/// Responds with the number of requests it has handled.
+struct Counter(Arc<std::sync::atomic::AtomicU8>);
+
+#[async_trait]
+impl Application for Counter {
+    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
+        let count = self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+        let mut response = Frame::new(13);
+        response.set_meta_from_request(frame.meta());
+        response.set_len(1)?;
+        response.data_mut()[0] = count;
+        Ok(Response::Single(response))
+    }
+
+    fn application_id(&self) -> u8 {
+        13
+    }
+
+    fn application_name(&self) -> &'static str {
+        "Counter"
+    }
+}
+
+#[tokio::test]
+async fn test_dedup_retransmitted_request() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let count = Arc::new(std::sync::atomic::AtomicU8::new(0));
+    let server = TcspServerBuilder::new(Channel::new(tx_sender, rx_receiver))
+        .with_application_options(
+            Arc::new(Counter(Arc::clone(&count))),
+            ApplicationOptions::new().dedup(Duration::from_secs(1)),
+        )
+        .build()
+        .unwrap();
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    for (id, expected) in [(5, 1), (5, 1), (6, 2)] {
+        let mut req = Frame::new_from_slice(13, &[]).unwrap();
+        req.meta_mut().id = id;
+        rx_sender.send(req.try_into().unwrap()).await.unwrap();
+        let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+        assert_eq!(resp.data(), &[expected]);
+    }
+    assert_eq!(count.load(std::sync::atomic::Ordering::Relaxed), 2);
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+}
+
+/// Counts its requests, and takes `delay` to answer.
+struct SlowCounter {
+    count: Arc<AtomicU32>,
+    delay: Duration,
+}
+
+#[async_trait]
+impl Application for SlowCounter {
+    async fn handle(&self, frame: Frame, _mtu: u16) -> std::io::Result<Response> {
+        let count = self.count.fetch_add(1, Ordering::AcqRel) + 1;
+        tokio::time::sleep(self.delay).await;
+        let mut response = Frame::new(14);
+        response.set_meta_from_request(frame.meta());
+        response.set_len(1)?;
+        response.data_mut()[0] = count as u8;
+        Ok(Response::Single(response))
+    }
+
+    fn application_id(&self) -> u8 {
+        14
+    }
+
+    fn application_name(&self) -> &'static str {
+        "SlowCounter"
+    }
+}
+
+#[tokio::test]
+async fn test_dedup_handler_outlives_window() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let count = Arc::new(AtomicU32::new(0));
+    let server = TcspServerBuilder::new(Channel::new(tx_sender, rx_receiver))
+        .with_application_options(
+            Arc::new(SlowCounter {
+                count: Arc::clone(&count),
+                delay: Duration::from_millis(200),
+            }),
+            ApplicationOptions::new().dedup(Duration::from_millis(20)),
+        )
+        .build()
+        .unwrap();
+    tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    let mut req = Frame::new_from_slice(14, &[]).unwrap();
+    req.meta_mut().id = 9;
+    let bus_frame: BusFrame = req.try_into().unwrap();
+    rx_sender.send(bus_frame.clone()).await.unwrap();
+    // retransmitted after the window, while the first request is still handled
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    rx_sender.send(bus_frame).await.unwrap();
+
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(resp.data(), &[1]);
+    assert_eq!(count.load(Ordering::Acquire), 1);
+    assert!(timeout(Duration::from_millis(300), tx_receiver.recv())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_dedup_failure_not_replayed() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let count = Arc::new(AtomicU32::new(0));
+    let server = TcspServerBuilder::new(Channel::new(tx_sender, rx_receiver))
+        .with_application_options(
+            Arc::new(SlowCounter {
+                count: Arc::clone(&count),
+                delay: Duration::from_millis(100),
+            }),
+            ApplicationOptions::new()
+                .dedup(Duration::from_secs(1))
+                .timeout(Duration::from_millis(20)),
+        )
+        .build()
+        .unwrap();
+    tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    let mut req = Frame::new_from_slice(14, &[]).unwrap();
+    req.meta_mut().id = 9;
+    let bus_frame: BusFrame = req.try_into().unwrap();
+    // the retransmission is handled again, and times out again
+    for handled in 1..=2 {
+        rx_sender.send(bus_frame.clone()).await.unwrap();
+        let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+        let error = ErrorResponse::try_from(&resp).unwrap();
+        assert_eq!(error.status, ErrorStatus::Timeout);
+        assert_eq!(count.load(Ordering::Acquire), handled);
+    }
+}
+
+/// Hands its downlink over to the test.
+struct Ticker(Arc<std::sync::Mutex<Option<Downlink>>>);
+
+#[async_trait]
+impl Application for Ticker {
+    async fn handle(&self, _frame: Frame, _mtu: u16) -> std::io::Result<Response> {
+        Ok(Response::Empty)
+    }
+
+    fn application_id(&self) -> u8 {
+        14
+    }
+
+    fn application_name(&self) -> &'static str {
+        "Ticker"
+    }
+
+    async fn init(&self, downlink: Downlink) -> std::io::Result<()> {
+        *self.0.lock().unwrap() = Some(downlink);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_downlink() {
+    let (can_tx_sender, mut can_tx_receiver) = channel(32);
+    let (_can_rx_sender, can_rx_receiver) = channel(32);
+    let (uart_tx_sender, mut uart_tx_receiver) = channel(32);
+    let (_uart_rx_sender, uart_rx_receiver) = channel(32);
+    let slot = Arc::new(std::sync::Mutex::new(None));
+    let server = TcspServerBuilder::new(Channel::new(can_tx_sender, can_rx_receiver))
+        .with_adaptor(Channel::new(uart_tx_sender, uart_rx_receiver))
+        .with_application(Arc::new(Ticker(Arc::clone(&slot))))
+        .build()
+        .unwrap();
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+    let downlink = loop {
+        if let Some(downlink) = slot.lock().unwrap().clone() {
+            break downlink;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    };
+
+    // to every adaptor
+    downlink
+        .send(Frame::new_from_slice(14, &[1, 2]).unwrap())
+        .await
+        .unwrap();
+    for receiver in [&mut can_tx_receiver, &mut uart_tx_receiver] {
+        let frame: Frame = receiver.recv().await.unwrap().try_into().unwrap();
+        assert_eq!(frame.application(), 14);
+        assert_eq!(frame.data(), &[1, 2]);
+    }
+
+    // to one adaptor
+    downlink
+        .send_to(AdaptorId(1), Frame::new_from_slice(14, &[3]).unwrap())
+        .await
+        .unwrap();
+    let frame: Frame = uart_tx_receiver.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(frame.data(), &[3]);
+    assert!(can_tx_receiver.try_recv().is_err());
+
+    // larger than the mtu of the channel
+    let too_large = Frame::new_from_slice(14, &[0; 149]).unwrap();
+    assert!(downlink.send(too_large).await.is_err());
+    // frames of other applications are rejected
+    let foreign = Frame::new_from_slice(1, &[]).unwrap();
+    assert!(downlink.send(foreign).await.is_err());
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+}
+
+/// Answers with `data[0]` frames, as a vector for odd counts and as a stream otherwise.
+struct Chunks;
+
+#[async_trait]
+impl Application for Chunks {
+    async fn handle(&self, frame: Frame, mtu: u16) -> std::io::Result<Response> {
+        let count = frame.data()[0];
+        let meta = *frame.meta();
+        let chunk = move |index: u8| {
+            let mut response = Frame::new(15);
+            response.set_meta_from_request(&meta);
+            response.set_len(mtu)?;
+            response.data_mut()[0] = index;
+            Ok(response)
+        };
+        if count % 2 == 1 {
+            let frames = (0..count).map(chunk).collect::<std::io::Result<Vec<_>>>()?;
+            Ok(Response::Frames(frames))
+        } else {
+            Ok(Response::stream(futures_util::stream::iter(
+                (0..count).map(chunk),
+            )))
+        }
+    }
+
+    fn application_id(&self) -> u8 {
+        15
+    }
+
+    fn application_name(&self) -> &'static str {
+        "Chunks"
+    }
+}
+
+#[tokio::test]
+async fn test_multi_frame_response() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let server = TcspServerBuilder::new(Channel::new(tx_sender, rx_receiver))
+        .with_application_options(
+            Arc::new(Chunks),
+            ApplicationOptions::new().pacing(Duration::from_millis(20)),
+        )
+        .build()
+        .unwrap();
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    for count in [3, 4] {
+        let start = tokio::time::Instant::now();
+        let req = Frame::new_from_slice(15, &[count]).unwrap();
+        rx_sender.send(req.try_into().unwrap()).await.unwrap();
+        for index in 0..count {
+            let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+            assert_eq!(resp.data().len(), 148);
+            assert_eq!(resp.data()[0], index);
+        }
+        assert!(start.elapsed() >= Duration::from_millis(20) * u32::from(count - 1));
+    }
+    assert!(tx_receiver.try_recv().is_err());
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_register_at_runtime() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let server = TcspServerBuilder::new(Channel::new(tx_sender, rx_receiver))
+        .build()
+        .unwrap();
+    let server = Arc::new(server);
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn({
+        let server = Arc::clone(&server);
+        async move { server.listen().await.unwrap() }
+    });
+    let request = |rx_sender: &tokio::sync::mpsc::Sender<BusFrame>| {
+        let req = Frame::new_from_slice(13, &[]).unwrap();
+        rx_sender.try_send(req.try_into().unwrap()).unwrap();
+    };
+
+    request(&rx_sender);
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    let error = ErrorResponse::try_from(&resp).unwrap();
+    assert_eq!(error.status, ErrorStatus::UnknownApplication);
+
+    let count = Arc::new(std::sync::atomic::AtomicU8::new(0));
+    server
+        .register(Arc::new(Counter(Arc::clone(&count))), ApplicationOptions::new())
+        .await
+        .unwrap();
+    request(&rx_sender);
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(resp.data(), &[1]);
+    let result = server
+        .register(Arc::new(Counter(Arc::clone(&count))), ApplicationOptions::new())
+        .await;
+    assert!(matches!(
+        result,
+        Err(ServerError::DuplicateApplication { id: 13, .. })
+    ));
+
+    // the new application is initialized before it takes over
+    let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let lifecycle = Lifecycle {
+        id: 13,
+        fail_init: false,
+        events: Arc::clone(&events),
+    };
+    let previous = server
+        .replace(Arc::new(lifecycle), ApplicationOptions::new())
+        .await
+        .unwrap();
+    assert_eq!(previous.unwrap().application_name(), "Counter");
+    assert_eq!(*events.lock().unwrap(), ["init 13"]);
+    request(&rx_sender);
+    assert!(timeout(Duration::from_millis(100), tx_receiver.recv())
+        .await
+        .is_err());
+    assert_eq!(count.load(std::sync::atomic::Ordering::Relaxed), 1);
+
+    let removed = server.unregister(13).await;
+    assert_eq!(removed.unwrap().application_name(), "Lifecycle");
+    assert_eq!(*events.lock().unwrap(), ["init 13", "shutdown 13"]);
+    request(&rx_sender);
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    let error = ErrorResponse::try_from(&resp).unwrap();
+    assert_eq!(error.status, ErrorStatus::UnknownApplication);
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+    // an unregistered application is not shut down twice
+    assert_eq!(events.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_priority_classes() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let server = TcspServerBuilder::new(Channel::new(tx_sender, rx_receiver))
+        .with_max_in_flight(1)
+        .with_application(Arc::new(Sleepy(1)))
+        .with_application_options(
+            Arc::new(Sleepy(2)),
+            ApplicationOptions::new().priority(Priority::Low),
+        )
+        .with_application_options(
+            Arc::new(Sleepy(3)),
+            ApplicationOptions::new().priority(Priority::High),
+        )
+        .build()
+        .unwrap();
+    let server = Arc::new(server);
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn({
+        let server = Arc::clone(&server);
+        async move { server.listen().await.unwrap() }
+    });
+
+    // keeps the only handler slot busy for 100ms
+    let req = Frame::new_from_slice(1, &[10]).unwrap();
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    for application in [2, 2, 3] {
+        let req = Frame::new_from_slice(application, &[0]).unwrap();
+        rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert!(server.pending_frames_of(Priority::Low) >= 1);
+
+    let mut order = Vec::new();
+    for _ in 0..4 {
+        let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+        order.push(resp.application());
+    }
+    assert_eq!(order[0], 1);
+    // the high priority request overtakes the queued low priority one
+    assert_eq!(order[3], 2);
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_rate_limit() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let count = Arc::new(std::sync::atomic::AtomicU8::new(0));
+    let server = TcspServerBuilder::new(Channel::new(tx_sender, rx_receiver))
+        .with_rate_limit(RateLimit::new(1, 2))
+        .with_rate_limit_action(RateLimitAction::Reject)
+        .with_application(Arc::new(Counter(Arc::clone(&count))))
+        .with_application_options(
+            Arc::new(Sleepy(1)),
+            ApplicationOptions::new().rate_limit(RateLimit::new(1, 1)),
+        )
+        .build()
+        .unwrap();
+    let server = Arc::new(server);
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn({
+        let server = Arc::clone(&server);
+        async move { server.listen().await.unwrap() }
+    });
+    let mut statuses = Vec::new();
+    for (src_id, application) in [(9, 13), (9, 13), (9, 13), (10, 1), (10, 1)] {
+        let mut req = Frame::new_from_slice(application, &[0]).unwrap();
+        req.meta_mut().src_id = src_id;
+        rx_sender.send(req.try_into().unwrap()).await.unwrap();
+        let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+        statuses.push(ErrorResponse::try_from(&resp).ok().map(|error| error.status));
+    }
+    let limited = Some(ErrorStatus::RateLimited);
+    assert_eq!(statuses, [None, None, limited, None, limited]);
+    assert_eq!(count.load(std::sync::atomic::Ordering::Relaxed), 2);
+    assert_eq!(server.rate_limited_frames(), 2);
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_access_policy() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let count = Arc::new(std::sync::atomic::AtomicU8::new(0));
+    let server = TcspServerBuilder::new(Channel::new(tx_sender, rx_receiver))
+        .with_access_policy(AccessPolicy::new().allow(13, [0]))
+        .with_application(Arc::new(Counter(Arc::clone(&count))))
+        .build()
+        .unwrap();
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    let mut req = Frame::new_from_slice(13, &[]).unwrap();
+    req.meta_mut().src_id = 5;
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    let error = ErrorResponse::try_from(&resp).unwrap();
+    assert_eq!(error.status, ErrorStatus::PermissionDenied);
+    assert_eq!(error.application, 13);
+
+    let req = Frame::new_from_slice(13, &[]).unwrap();
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(resp.data(), &[1]);
+    assert_eq!(count.load(std::sync::atomic::Ordering::Relaxed), 1);
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_diagnostics() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let server = TcspServerBuilder::new(Channel::new(tx_sender, rx_receiver))
+        .with_application(Arc::new(EchoCommand {}))
+        .with_application(Arc::new(Diagnostics::new()))
+        .build()
+        .unwrap();
+    let server = Arc::new(server);
+    let shutdown = server.shutdown_handle();
+    let listening = tokio::spawn({
+        let server = Arc::clone(&server);
+        async move { server.listen().await.unwrap() }
+    });
+
+    let req = Frame::new_from_slice(99, &[]).unwrap();
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(
+        ErrorResponse::try_from(&resp).unwrap().status,
+        ErrorStatus::UnknownApplication
+    );
+    let req = BusFrame::new(FrameMeta::default(), &[0x10, 2]).unwrap();
+    rx_sender.send(req).await.unwrap();
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(
+        ErrorResponse::try_from(&resp).unwrap().status,
+        ErrorStatus::VersionMismatch
+    );
+    let req = Frame::new_from_slice(2, &[1]).unwrap();
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let _echo = tx_receiver.recv().await.unwrap();
+
+    let req = Frame::new_from_slice(7, &[]).unwrap();
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    let counters = |data: &[u8]| {
+        data.chunks(4)
+            .map(|chunk| u32::from_be_bytes(chunk.try_into().unwrap()))
+            .collect::<Vec<_>>()
+    };
+    // received, version mismatches, unknown applications and handled
+    assert_eq!(counters(resp.data()), [4, 0, 0, 0, 1, 1, 0, 2, 0, 0]);
+    let resp: Frame = tx_receiver.recv().await.unwrap().try_into().unwrap();
+    assert_eq!(resp.data()[0], 0);
+    // the adaptor has sent the three responses before
+    assert_eq!(counters(&resp.data()[1..]), [4, 3, 0, 0, 0, 0]);
+
+    let stats = server.stats();
+    assert_eq!(stats.received, 4);
+    assert_eq!(stats.adaptors.len(), 1);
+    assert_eq!(stats.adaptors[0].sent, 5);
+
+    shutdown.shutdown();
+    listening.await.unwrap();
+}
+
+/// An adaptor whose device is unplugged for the first `unplugged` receives.
+struct Unplugged {
+    channel: Channel,
+    unplugged: AtomicU32,
+    reopens: Arc<AtomicU32>,
+}
+
+#[async_trait]
+impl DeviceAdaptor for Unplugged {
+    async fn send(&self, frame: BusFrame) -> Result<(), DeviceAdaptorError> {
+        self.channel.send(frame).await
+    }
+
+    async fn recv(&self) -> Result<BusFrame, DeviceAdaptorError> {
+        let unplugged = self
+            .unplugged
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1));
+        if unplugged.is_ok() {
+            return Err(std::io::Error::from_raw_os_error(libc::ENODEV).into());
+        }
+        self.channel.recv().await
+    }
+
+    fn mtu(&self, flag: FrameFlag) -> usize {
+        self.channel.mtu(flag)
+    }
+
+    async fn reopen(&self) -> Result<(), DeviceAdaptorError> {
+        self.reopens.fetch_add(1, Ordering::AcqRel);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_adaptor_recovery() {
+    let (tx_sender, mut tx_receiver) = channel(32);
+    let (rx_sender, rx_receiver) = channel(32);
+    let reopens = Arc::new(AtomicU32::new(0));
+    let adaptor = Unplugged {
+        channel: Channel::new(tx_sender, rx_receiver),
+        unplugged: AtomicU32::new(3),
+        reopens: Arc::clone(&reopens),
+    };
+    let server = TcspServerBuilder::new(adaptor)
+        .with_application(Arc::new(EchoCommand {}))
+        .with_backoff(Backoff::new(
+            Duration::from_millis(1),
+            Duration::from_millis(5),
+        ))
+        .build()
+        .unwrap();
+    tokio::spawn(async move {
+        server.listen().await.unwrap();
+    });
+
+    let req = Frame::new_from_slice(2, &[1, 2, 3]).unwrap();
+    rx_sender.send(req.try_into().unwrap()).await.unwrap();
+    let resp: Frame = timeout(Duration::from_secs(1), tx_receiver.recv())
+        .await
+        .unwrap()
+        .unwrap()
+        .try_into()
+        .unwrap();
+    assert_eq!(&resp.data()[..3], [1, 2, 3]);
+    assert_eq!(reopens.load(Ordering::Acquire), 3);
 }
 
 #[tokio::test]
@@ -104,7 +1420,7 @@ async fn generate_can_udp_request_example() {
     )
     .unwrap();
     for frame in result.into_iter() {
-        send_using_ty_protocol(&can_frames, 0, 0, frame.try_into().unwrap())
+        send_using_ty_protocol(&can_frames, 0, frame.try_into().unwrap())
             .await
             .unwrap();
     }
@@ -1,4 +1,5 @@
 use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 /// Check if the current process has root privilege
 pub(crate) fn has_root_privilege() -> bool{
@@ -12,3 +13,17 @@ pub(crate) fn has_root_privilege() -> bool{
         }
     }
 }
+
+/// A monotonic event counter.
+#[derive(Debug, Default)]
+pub(crate) struct Counter(AtomicU64);
+
+impl Counter {
+    pub(crate) fn incr(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
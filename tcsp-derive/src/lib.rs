@@ -0,0 +1,159 @@
+//! `#[derive(Encode, Decode)]` for the payload codec of `tcsp`, see `tcsp::Encode`.
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, spanned::Spanned, Data, DataEnum, DeriveInput, Fields, Generics,
+};
+
+#[proc_macro_derive(Encode)]
+pub fn derive_encode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_encode(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(Decode)]
+pub fn derive_decode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_decode(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_encode(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let generics = add_bound(input.generics.clone(), quote!(::tcsp::Encode));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let fields = data.fields.iter().enumerate().map(|(index, field)| {
+                let member = match &field.ident {
+                    Some(ident) => quote!(#ident),
+                    None => {
+                        let index = syn::Index::from(index);
+                        quote!(#index)
+                    }
+                };
+                quote!(::tcsp::Encode::encode(&self.#member, buf)?;)
+            });
+            quote! {
+                #(#fields)*
+                ::core::result::Result::Ok(())
+            }
+        }
+        Data::Enum(data) => {
+            let repr = enum_repr(input)?;
+            let variants = unit_variants(data)?;
+            quote! {
+                let value = match self {
+                    #(Self::#variants => Self::#variants as #repr,)*
+                };
+                ::tcsp::Encode::encode(&value, buf)
+            }
+        }
+        Data::Union(_) => return Err(syn::Error::new(input.span(), "unions are not supported")),
+    };
+    Ok(quote! {
+        impl #impl_generics ::tcsp::Encode for #name #ty_generics #where_clause {
+            fn encode(
+                &self,
+                buf: &mut ::std::vec::Vec<u8>,
+            ) -> ::core::result::Result<(), ::tcsp::CodecError> {
+                #body
+            }
+        }
+    })
+}
+
+fn expand_decode(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let generics = add_bound(input.generics.clone(), quote!(::tcsp::Decode));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let decode = quote!(::tcsp::Decode::decode(buf)?);
+            let value = match &data.fields {
+                Fields::Named(fields) => {
+                    let names = fields.named.iter().map(|field| &field.ident);
+                    quote!(Self { #(#names: #decode,)* })
+                }
+                Fields::Unnamed(fields) => {
+                    let fields = fields.unnamed.iter().map(|_| &decode);
+                    quote!(Self(#(#fields,)*))
+                }
+                Fields::Unit => quote!(Self),
+            };
+            quote!(::core::result::Result::Ok(#value))
+        }
+        Data::Enum(data) => {
+            let repr = enum_repr(input)?;
+            let variants = unit_variants(data)?;
+            let ty = name.to_string();
+            quote! {
+                let value: #repr = ::tcsp::Decode::decode(buf)?;
+                #(
+                    if value == Self::#variants as #repr {
+                        return ::core::result::Result::Ok(Self::#variants);
+                    }
+                )*
+                ::core::result::Result::Err(::tcsp::CodecError::InvalidValue {
+                    ty: #ty,
+                    value: value.into(),
+                })
+            }
+        }
+        Data::Union(_) => return Err(syn::Error::new(input.span(), "unions are not supported")),
+    };
+    Ok(quote! {
+        impl #impl_generics ::tcsp::Decode for #name #ty_generics #where_clause {
+            fn decode(buf: &mut &[u8]) -> ::core::result::Result<Self, ::tcsp::CodecError> {
+                #body
+            }
+        }
+    })
+}
+
+fn add_bound(mut generics: Generics, bound: TokenStream2) -> Generics {
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#bound));
+    }
+    generics
+}
+
+/// The integer type of an enum, given by `#[repr]`. Default is `u8`.
+fn enum_repr(input: &DeriveInput) -> syn::Result<Ident> {
+    let mut repr = format_ident!("u8");
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("repr"))
+    {
+        attr.parse_nested_meta(|meta| {
+            match meta.path.get_ident() {
+                Some(ident)
+                    if ["u8", "u16", "u32", "u64"].contains(&ident.to_string().as_str()) =>
+                {
+                    repr = ident.clone();
+                }
+                _ => return Err(meta.error("expect repr u8, u16, u32 or u64")),
+            }
+            Ok(())
+        })?;
+    }
+    Ok(repr)
+}
+
+fn unit_variants(data: &DataEnum) -> syn::Result<Vec<&Ident>> {
+    data.variants
+        .iter()
+        .map(|variant| match variant.fields {
+            Fields::Unit => Ok(&variant.ident),
+            _ => Err(syn::Error::new(
+                variant.span(),
+                "only enums without fields are supported",
+            )),
+        })
+        .collect()
+}